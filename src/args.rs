@@ -1,6 +1,25 @@
+use std::path::PathBuf;
+
+use chrono::DateTime;
+use chrono::FixedOffset;
+use chrono::NaiveDate;
+use chrono::NaiveTime;
+use chrono::Weekday;
+use clap::ArgGroup;
 use clap::Parser;
 use clap::Subcommand;
 
+use crate::feed::FeedFormat;
+use crate::format::Template;
+use crate::merge::DataFile;
+use crate::models::schedule::parse_air_date;
+use crate::models::show::Column;
+use crate::models::show::SortKey;
+use crate::models::watched::Day;
+use crate::models::watched::Outcome;
+use crate::report::ReportFormat;
+use crate::table::ColorChoice;
+
 #[derive(Parser)]
 #[command(author, version)]
 #[command(about = std::include_str!("description.txt"))]
@@ -15,11 +34,47 @@ pub struct Args {
 #[derive(Subcommand)]
 pub enum UserCommands {
     /// List all the shows you're currently watching, with their episode information.
+    /// The fields of `--format` are: title, episode, downloaded, total, backlog, link, dlink,
+    /// status, rewatch, last_watched, added, updated.
     #[command(visible_alias = "s")]
     Shows {
         /// Display the links of each show as well.
-        #[arg(short, long)]
+        #[arg(short, long, conflicts_with = "format")]
         links: bool,
+        /// List the shows you put on hold, rather than the ones you're watching.
+        #[arg(long)]
+        on_hold: bool,
+        /// How to order the shows.
+        #[arg(long, value_enum, default_value_t)]
+        sort: SortKey,
+        /// Reverse the order.
+        #[arg(short, long)]
+        reverse: bool,
+        /// Only list the shows you downloaded more episodes of than you watched.
+        #[arg(long)]
+        has_backlog: bool,
+        /// Only list the shows without an episode link.
+        #[arg(long)]
+        no_link: bool,
+        /// Only list the shows you haven't changed in this many days.
+        #[arg(long, value_name = "DAYS")]
+        stale: Option<u32>,
+        #[command(flatten)]
+        format: FormatArgs,
+        /// The columns to print, separated by commas.
+        #[arg(
+            short,
+            long,
+            value_enum,
+            value_delimiter = ',',
+            default_values_t = Column::DEFAULT,
+            conflicts_with_all = ["links", "format"]
+        )]
+        columns: Vec<Column>,
+        /// Whether to color the shows that have downloaded episodes left to watch (green), that
+        /// are close to their finale (magenta), or that you haven't touched in two weeks (dim).
+        #[arg(long, value_enum, default_value_t)]
+        color: ColorChoice,
     },
     /// Print the next episode's link.
     /// This works by appending the watched episode numer + 1 onto the link.
@@ -45,6 +100,20 @@ pub enum UserCommands {
         #[arg(short, long)]
         web: bool,
     },
+    /// Play the next episode to watch from the download directory of a show (see `dir`),
+    /// in the player specified in $GLAZA_PLAYER (`mpv` by default).
+    /// Once the player exits successfully, you're asked whether to mark the episode as watched.
+    /// If the player is mpv, glaza instead follows the playback: the episode is marked as watched
+    /// if you got past `mpv_threshold` percent of it (85 by default), and otherwise, the next
+    /// `play` continues from where you stopped.
+    #[command(visible_alias = "p")]
+    Play {
+        show: String,
+        /// Mark the episode as watched without asking.
+        /// Doesn't apply to mpv, where the playback progress decides.
+        #[arg(short, long)]
+        mark: bool,
+    },
     /// Print the episode link of a show.
     /// This is most useful for shows that don't support `watch` due to
     /// having non-standard urls.
@@ -79,7 +148,92 @@ pub enum UserCommands {
     /// It will be used for the `save` and `pdlink` subcommands.
     /// And also, as a fallback if you don't define an episode link.
     Dlink { show: String, link: String },
+    /// Set when the episodes of a show come out, so that `due` can tell you
+    /// about the ones you haven't watched or downloaded yet.
+    /// Either give a weekly schedule with `--weekday`, `--time` and `--since`,
+    /// or the list of every air date with `--dates` (or from a calendar with `--ics`).
+    #[command(group(ArgGroup::new("kind").required(true).args(["weekday", "dates", "ics", "clear"])))]
+    Schedule {
+        show: String,
+        /// The weekday new episodes come out on, like `mon` or `friday`.
+        #[arg(short, long, requires_all = ["time", "since"])]
+        weekday: Option<Weekday>,
+        /// The time new episodes come out at, like `21:00`.
+        #[arg(short, long, requires = "weekday")]
+        time: Option<NaiveTime>,
+        /// The timezone `--time` is in, like `+09:00`. Defaults to your local timezone.
+        #[arg(short, long, requires = "weekday", allow_hyphen_values = true)]
+        utc_offset: Option<FixedOffset>,
+        /// The date (YYYY-MM-DD) on or after which the first weekly episode comes out.
+        #[arg(short, long, requires = "weekday")]
+        since: Option<NaiveDate>,
+        /// The episode that comes out on the first weekly air date.
+        #[arg(short, long, default_value_t = 1, requires = "weekday")]
+        first: u32,
+        /// Comma separated air dates of every episode, starting with episode 1.
+        /// Each is either RFC 3339, `YYYY-MM-DD HH:MM` or `YYYY-MM-DD`.
+        #[arg(short, long, value_delimiter = ',', value_parser = parse_air_date)]
+        dates: Vec<DateTime<FixedOffset>>,
+        /// An iCalendar (.ics) file with an event for every episode, starting with episode 1,
+        /// to take the air dates from.
+        #[arg(long)]
+        ics: Option<PathBuf>,
+        /// Remove the schedule of the show.
+        #[arg(short, long)]
+        clear: bool,
+    },
+    /// Print an iCalendar (.ics) file with an event for every upcoming episode of the shows with a
+    /// schedule, for calendar apps, like `glaza calendar > glaza.ics`.
+    /// Each event has the episode number and its link.
+    Calendar {
+        /// How many weeks ahead weekly schedules go, unless the show has a total.
+        #[arg(short, long, default_value_t = 8)]
+        weeks: u32,
+    },
+    /// List the shows that have episodes out that you haven't watched or downloaded yet.
+    /// Only shows with a schedule (see `schedule`) are considered.
+    Due,
+    /// Set the directory you download the episodes of a show into, so that `scan`
+    /// can find out what episode you downloaded last by itself.
+    Dir {
+        show: String,
+        dir: PathBuf,
+        /// The filename pattern of the episodes.
+        /// Either a template where `{ep}` stands for the episode number, like
+        /// `One Piece - {ep} [1080p].mkv`, or a regex that captures the episode number
+        /// in a group named `ep` or in its first group, like `E(\d+)\.mkv$`.
+        #[arg(short, long)]
+        pattern: String,
+    },
+    /// Update the downloaded episode of a show by looking at the files in its download directory
+    /// (see `dir`), instead of having to use `download`.
+    /// The downloaded episode goes up to the highest one of the contiguous run of episodes that
+    /// follows it, and never down.
+    /// Without a show, every show that has a download directory is scanned.
+    Scan {
+        show: Option<String>,
+        /// Only print what would change, without changing anything.
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+    /// Look for a show in every list at once: the shows you're watching or put on hold, watch
+    /// later, and your watched list.
+    /// Prints where each match is, with the episode you're on, or when you finished or dropped it.
+    Find {
+        /// Matched case-insensitively: exact matches if there are any, otherwise every title
+        /// that contains it.
+        pattern: String,
+    },
+    /// Set how many episodes a show has, or forget it if you leave it out.
+    Total { show: String, total: Option<u32> },
+    /// Put a show on hold: it keeps its progress and links, but `shows` and `due` stop listing
+    /// it until you `resume` it.
+    Hold { show: String },
+    /// Take a show off hold.
+    Resume { show: String },
     /// Start a new show, putting it in your ‘currently watching’ list.
+    /// If you finished the show before, it becomes a rewatch, and its watched line will say
+    /// `(rewatch #N)` once you finish or drop it.
     #[command(visible_alias = "new")]
     #[command(visible_alias = "n")]
     Start {
@@ -104,6 +258,9 @@ pub enum UserCommands {
         /// This is to help you realize if you misspelled a show title.
         #[arg(short, long)]
         grab: bool,
+        /// When you started it, if not today: a YYYY-MM-DD date, or `yesterday`.
+        #[arg(long)]
+        date: Option<Day>,
     },
     /// Finish a show, putting it in your watched list.
     #[command(visible_alias = "f")]
@@ -121,6 +278,9 @@ pub enum UserCommands {
         /// If `--grab`/`-g` is specified, this flag is ignored.
         #[arg(short, long)]
         fresh: bool,
+        /// The day it goes in the watched list with: a YYYY-MM-DD date, `yesterday` or `today`.
+        #[arg(long, default_value = "today")]
+        date: Day,
     },
     /// Drop a show, putting it in your watched list.
     /// The distinction from `finish` is that to the left of the show name in
@@ -144,6 +304,9 @@ pub enum UserCommands {
         /// If `--grab`/`-g` is specified, this flag is ignored.
         #[arg(short, long)]
         fresh: bool,
+        /// The day it goes in the watched list with: a YYYY-MM-DD date, `yesterday` or `today`.
+        #[arg(long, default_value = "today")]
+        date: Day,
     },
     /// Remove a show from the list without putting it in your watched list.
     /// This is useful if you accidentally added a show you didn't mean to,
@@ -151,6 +314,16 @@ pub enum UserCommands {
     #[command(visible_alias = "rm")]
     #[command(visible_alias = "delete")]
     Remove { show: String },
+    /// Give a show you're watching another title, like to fix a misspelling, keeping everything
+    /// else about it: the episodes, the links, the schedule and so on.
+    /// Its episodes in the episode log get renamed along with it.
+    Rename {
+        show: String,
+        new_title: String,
+        /// Also rename it in your watch later list and every time it's in your watched list.
+        #[arg(short, long)]
+        everywhere: bool,
+    },
     /// Add a new show to your watch later list.
     #[command(visible_alias = "later")]
     #[command(visible_alias = "a")]
@@ -159,10 +332,196 @@ pub enum UserCommands {
     #[command(visible_alias = "c")]
     Discard { show: String },
     /// Print the entire contents of your watch later file.
-    Wl,
+    /// The fields of `--format` are: title, status, episode, date, rewatch.
+    Wl {
+        #[command(flatten)]
+        format: FormatArgs,
+    },
+    /// Print an Atom or RSS feed of the shows you finished and dropped, newest first, for putting
+    /// up on a site, like from a `post-finish` hook or a git hook.
+    Feed {
+        #[arg(short, long, value_enum, default_value_t = FeedFormat::Atom)]
+        format: FeedFormat,
+        /// Only the shows finished or dropped on this day (YYYY-MM-DD) or later.
+        #[arg(short, long)]
+        since: Option<NaiveDate>,
+        /// Where the feed is published.
+        #[arg(short, long)]
+        link: Option<String>,
+    },
+    /// Print a report of a year: the shows you finished and dropped in it, month by month, how many
+    /// episodes you watched (according to the episode log), the shows that took you the longest,
+    /// and the ones you dropped, with the episode you dropped them at.
+    Report {
+        /// The year to report on, this one by default.
+        #[arg(short, long)]
+        year: Option<i32>,
+        #[arg(short, long, value_enum, default_value_t = ReportFormat::Md)]
+        format: ReportFormat,
+    },
     /// Print the entire contents of your watched list.
+    /// The fields of `--format` are: title, status, episode, date, rewatch.
     #[command(visible_alias = "past")]
-    Watched,
+    #[command(args_conflicts_with_subcommands = true)]
+    Watched {
+        #[command(subcommand)]
+        action: Option<WatchedCommands>,
+        #[command(flatten)]
+        format: FormatArgs,
+    },
+    /// Pull the data directory from its git remote (rebasing your local commits on top), and then
+    /// push it there.
+    /// The remote is `origin`, unless you set `remote` in your config.
+    Sync {
+        /// Use this remote instead of the configured one.
+        #[arg(short, long)]
+        remote: Option<String>,
+    },
+    /// Print the timeline of a show (or of every show), as told by the commits that `--git` made:
+    /// when you started it, every episode you watched, link changes, and when you finished or
+    /// dropped it.
+    /// The show is matched against every title that ever appeared in the history.
+    History { show: Option<String> },
+    /// Merge two versions of a data file, as a git merge driver.
+    /// `--git` sets this up in the data directory, so you don't need to call this yourself.
+    #[command(hide = true)]
+    MergeDriver {
+        kind: DataFile,
+        /// The common ancestor version (`%O`).
+        base: PathBuf,
+        /// Our version (`%A`), that the result of the merge is written into.
+        ours: PathBuf,
+        /// Their version (`%B`).
+        theirs: PathBuf,
+    },
+    /// Print the log of every episode change you made with `episode`,
+    /// along with when you made it.
+    Log {
+        /// Only print the entries of this show.
+        /// Matched against every title that ever appeared in the log.
+        show: Option<String>,
+        /// Only print the entries made on this date (YYYY-MM-DD) or later.
+        #[arg(short, long)]
+        since: Option<NaiveDate>,
+    },
+    /// Check the data files for mistakes: yaml errors in current.yml (with where they are), links
+    /// that aren't urls, shows watched further than downloaded, malformed watched lines, and
+    /// blank, duplicate or already started watch later lines.
+    /// Exits unsuccessfully if it finds any problems.
+    Doctor {
+        /// Fix the problems that are safe to fix: remove blank lines, duplicate watch later
+        /// entries, and watch later entries of shows you already started.
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Serve an html dashboard of your lists, that can also mark episodes watched, and finish or
+    /// drop shows, for using glaza from your phone, and a JSON API of the same, for scripts.
+    /// With `--git`, the changes made through it get committed, like they do from here.
+    /// With `api_token` in your config, the API and the dashboard buttons want it, but without
+    /// one there's no authentication, so only bind it to addresses on networks you trust.
+    Serve {
+        /// The address to listen on.
+        #[arg(short, long, default_value = "127.0.0.1:8080")]
+        bind: String,
+    },
+    /// Look at and restore the snapshots that are taken of the data files right before every
+    /// change, in the `backups` directory of the data directory.
+    /// How many are kept is set by `backups` in your config.
+    Backup {
+        #[command(subcommand)]
+        action: BackupCommands,
+    },
+}
+
+impl UserCommands {
+    /// Whether the command only looks at the data files, so it can run while something else is
+    /// changing them, like from a hook of that something.
+    pub fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            Self::Shows { .. }
+                | Self::Watch { .. }
+                | Self::Save { .. }
+                | Self::Plink { .. }
+                | Self::Pdlink { .. }
+                | Self::Due
+                | Self::Calendar { .. }
+                | Self::Find { .. }
+                | Self::Wl { .. }
+                | Self::Feed { .. }
+                | Self::Report { .. }
+                | Self::Watched { action: None, .. }
+                | Self::History { .. }
+                | Self::Log { .. }
+                | Self::Doctor { fix: false }
+                | Self::Backup {
+                    action: BackupCommands::List | BackupCommands::Diff { .. }
+                }
+        )
+    }
+}
+
+#[derive(Subcommand)]
+pub enum WatchedCommands {
+    /// Change the latest entry (by date) of a show in your watched list.
+    #[command(group(ArgGroup::new("change").required(true).multiple(true).args(["date", "title", "outcome", "episode"])))]
+    Edit {
+        show: String,
+        /// The day you finished or dropped it: a YYYY-MM-DD date, `yesterday` or `today`.
+        #[arg(short, long)]
+        date: Option<Day>,
+        /// Its new title.
+        #[arg(short, long)]
+        title: Option<String>,
+        /// Whether you finished or dropped it.
+        #[arg(short, long, value_enum)]
+        outcome: Option<Outcome>,
+        /// The episode you dropped it at.
+        #[arg(short, long)]
+        episode: Option<u32>,
+    },
+    /// Remove the latest entry (by date) of a show from your watched list.
+    #[command(visible_alias = "remove")]
+    Rm { show: String },
+}
+
+#[derive(Subcommand)]
+pub enum BackupCommands {
+    /// List every snapshot, oldest first, with the files in it.
+    List,
+    /// Put the files of a snapshot back in the data directory.
+    /// The versions they replace are snapshotted as well, so this can be undone.
+    Restore {
+        /// The id of the snapshot, or the start of it.
+        id: String,
+    },
+    /// Show what changed in the files of a snapshot since it was taken.
+    Diff {
+        /// The id of the snapshot, or the start of it.
+        id: String,
+    },
+}
+
+/// The `--format` of the commands that list shows.
+#[derive(clap::Args)]
+pub struct FormatArgs {
+    /// Print each show in this format instead, like `{title:<}[\t{episode}]`, with the
+    /// fields listed above.
+    /// `{field:<N}` and `{field:>N}` pad a field to N characters, and `{field:<}` pads it to the
+    /// longest one, to line it up. `[...]` is only printed if every field inside of it has a
+    /// value. `\t` and `\n` are tabs and newlines.
+    #[arg(short, long, allow_hyphen_values = true)]
+    pub format: Option<String>,
+}
+
+impl FormatArgs {
+    /// The template, checked against the `fields` of the command.
+    pub fn template(&self, fields: &[&'static str]) -> Result<Option<Template>, String> {
+        self.format
+            .as_deref()
+            .map(|format| Template::parse(format, fields))
+            .transpose()
+    }
 }
 
 #[cfg(test)]
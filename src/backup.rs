@@ -0,0 +1,175 @@
+//! Snapshots of the data files, taken right before they get changed.
+
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use chrono::Local;
+use similar::TextDiff;
+
+pub const BACKUPS_DIR: &str = "backups";
+const DEFAULT_KEEP: usize = 20;
+
+static KEEP: OnceLock<usize> = OnceLock::new();
+
+/// Sets how many snapshots to keep around, for the rest of the program.
+/// 0 disables snapshots.
+pub fn set_keep(keep: usize) {
+    KEEP.get_or_init(|| keep);
+}
+
+pub struct Snapshot {
+    pub id: String,
+    dir: PathBuf,
+}
+
+impl Snapshot {
+    pub fn files(&self) -> Vec<String> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut files: Vec<String> = entries
+            .flatten()
+            .filter_map(|entry| entry.file_name().to_str().map(|name| name.to_owned()))
+            .collect();
+        files.sort();
+        files
+    }
+}
+
+fn backups_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join(BACKUPS_DIR)
+}
+
+/// Copies `file_path` into `backups/<timestamp>/` next to it, and removes the oldest snapshots
+/// above the amount to keep.
+/// Files saved within the same millisecond end up in the same snapshot.
+pub fn snapshot(file_path: &Path) -> Result<(), String> {
+    let keep = *KEEP.get().unwrap_or(&DEFAULT_KEEP);
+    if keep == 0 || !file_path.exists() {
+        return Ok(());
+    }
+    let (Some(data_dir), Some(file_name)) = (file_path.parent(), file_path.file_name()) else {
+        return Ok(());
+    };
+    let id = Local::now().format("%Y-%m-%dT%H-%M-%S%.3f").to_string();
+    let mut snapshot_dir = backups_dir(data_dir).join(&id);
+    // the same file saved twice within a millisecond needs a snapshot of its own
+    let mut counter = 1;
+    while snapshot_dir.join(file_name).exists() {
+        snapshot_dir = backups_dir(data_dir).join(format!("{id}-{counter}"));
+        counter += 1;
+    }
+    fs::create_dir_all(&snapshot_dir)
+        .map_err(|_| format!("couldn't create the backup directory {}", snapshot_dir.display()))?;
+    fs::copy(file_path, snapshot_dir.join(file_name))
+        .map_err(|_| format!("couldn't back up {}", file_path.display()))?;
+    let snapshots = list(data_dir);
+    for old in snapshots.iter().take(snapshots.len().saturating_sub(keep)) {
+        fs::remove_dir_all(&old.dir).map_err(|_| format!("couldn't remove the old backup {}", old.id))?;
+    }
+    Ok(())
+}
+
+/// Every snapshot, oldest first.
+pub fn list(data_dir: &Path) -> Vec<Snapshot> {
+    let Ok(entries) = fs::read_dir(backups_dir(data_dir)) else {
+        return Vec::new();
+    };
+    let mut snapshots: Vec<Snapshot> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            Some(Snapshot {
+                id: entry.file_name().to_str()?.to_owned(),
+                dir: entry.path(),
+            })
+        })
+        .collect();
+    snapshots.sort_by(|a, b| a.id.cmp(&b.id));
+    snapshots
+}
+
+/// Finds the snapshot whose id is `id`, or starts with it, if that's unambiguous.
+pub fn find(data_dir: &Path, id: &str) -> Result<Snapshot, String> {
+    let mut candidates: Vec<Snapshot> = list(data_dir)
+        .into_iter()
+        .filter(|snapshot| snapshot.id.starts_with(id))
+        .collect();
+    if let Some(exact) = candidates.iter().position(|snapshot| snapshot.id == id) {
+        return Ok(candidates.swap_remove(exact));
+    }
+    match candidates.len() {
+        0 => Err(format!("there's no backup '{id}'")),
+        1 => Ok(candidates.remove(0)),
+        _ => Err(format!(
+            "backup '{id}' is ambiguous between: {}",
+            candidates
+                .iter()
+                .map(|snapshot| snapshot.id.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Copies the files of the snapshot back into the data directory, snapshotting the versions they
+/// replace first.
+pub fn restore(data_dir: &Path, backup: &Snapshot) -> Result<(), String> {
+    // taking new snapshots can remove this backup, if it's the oldest
+    let mut restored = Vec::new();
+    for file_name in backup.files() {
+        let contents = fs::read(backup.dir.join(&file_name))
+            .map_err(|_| format!("couldn't read {file_name} from backup {}", backup.id))?;
+        restored.push((file_name, contents));
+    }
+    for (file_name, contents) in restored {
+        let target = data_dir.join(&file_name);
+        snapshot(&target)?;
+        fs::write(&target, contents).map_err(|_| format!("couldn't restore {file_name}"))?;
+    }
+    Ok(())
+}
+
+/// A unified diff from each file in the snapshot to its current version.
+pub fn diff(data_dir: &Path, snapshot: &Snapshot) -> String {
+    let mut output = String::new();
+    for file_name in snapshot.files() {
+        let old = fs::read_to_string(snapshot.dir.join(&file_name)).unwrap_or_default();
+        let new = fs::read_to_string(data_dir.join(&file_name)).unwrap_or_default();
+        let diff = TextDiff::from_lines(&old, &new);
+        output.push_str(
+            &diff
+                .unified_diff()
+                .header(&format!("{}/{file_name}", snapshot.id), &file_name)
+                .to_string(),
+        );
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::find;
+    use super::list;
+    use super::restore;
+    use super::snapshot;
+
+    #[test]
+    fn restore_brings_back_the_snapshot() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let file_path = data_dir.path().join("watch-later.txt");
+        fs::write(&file_path, "Frieren\n").unwrap();
+        snapshot(&file_path).unwrap();
+        fs::write(&file_path, "").unwrap();
+        let id = list(data_dir.path())[0].id.clone();
+        let backup = find(data_dir.path(), &id[..10]).unwrap();
+        restore(data_dir.path(), &backup).unwrap();
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "Frieren\n");
+        // the emptied file got snapshotted on the way
+        assert_eq!(list(data_dir.path()).len(), 2);
+    }
+}
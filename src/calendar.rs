@@ -0,0 +1,208 @@
+//! iCalendar (`.ics`) files of when the episodes of your shows air: exporting them for calendar
+//! apps, and reading the air dates out of one for `schedule --ics`.
+
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::FixedOffset;
+use chrono::Local;
+use chrono::NaiveDate;
+use chrono::NaiveDateTime;
+use chrono::NaiveTime;
+use chrono::TimeZone;
+use chrono::Utc;
+use url::form_urlencoded;
+
+use crate::models::schedule::Schedule;
+use crate::models::show::CurrentRepo;
+
+/// Lines longer than this (in bytes) get folded, as iCalendar wants.
+const MAX_LINE: usize = 75;
+
+/// Renders a calendar with an event for every air date of the shows you're watching, starting at
+/// the episode after the one you watched last.
+/// Weekly schedules never end, so they stop at the total if the show has one, and otherwise
+/// `weeks` weeks from now.
+pub fn calendar(current_model: &CurrentRepo, weeks: u32) -> String {
+    let until = Local::now().fixed_offset() + Duration::weeks(weeks.into());
+    let stamp = ics_date(Utc::now().fixed_offset());
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_owned(),
+        "VERSION:2.0".to_owned(),
+        "PRODID:-//glaza//glaza//EN".to_owned(),
+        "CALSCALE:GREGORIAN".to_owned(),
+    ];
+    for (title, show) in current_model.shows() {
+        let Some(schedule) = show.schedule.as_ref().filter(|_| show.status.is_watching()) else {
+            continue;
+        };
+        let endless = matches!(schedule, Schedule::Weekly { .. });
+        let uid_title: String = form_urlencoded::byte_serialize(title.as_str().as_bytes()).collect();
+        let has_link = !current_model.get_link(&title, false).is_empty();
+        let air_dates = schedule
+            .air_dates()
+            .skip_while(|(episode, _)| *episode <= show.episode)
+            .take_while(|(episode, date)| match show.total {
+                Some(total) => *episode <= total,
+                None => !endless || *date <= until,
+            });
+        for (episode, date) in air_dates {
+            let mut description = match show.total {
+                Some(total) => format!("Episode {episode} of {total}"),
+                None => format!("Episode {episode}"),
+            };
+            if has_link {
+                description.push_str(&format!("\n{}", current_model.get_episode_link(&title, episode)));
+            }
+            lines.extend([
+                "BEGIN:VEVENT".to_owned(),
+                format!("UID:{uid_title}-ep{episode}@glaza"),
+                format!("DTSTAMP:{stamp}"),
+                format!("DTSTART:{}", ics_date(date)),
+                format!("SUMMARY:{} ep{episode}", escape(title.as_str())),
+                format!("DESCRIPTION:{}", escape(&description)),
+                "END:VEVENT".to_owned(),
+            ]);
+        }
+    }
+    lines.push("END:VCALENDAR".to_owned());
+    lines
+        .iter()
+        .map(|line| fold(line))
+        .collect::<Vec<_>>()
+        .join("\r\n")
+        + "\r\n"
+}
+
+/// The start of every event in an iCalendar file, in order.
+/// Dates without a time are taken to be midnight, and times without a timezone to be in your
+/// local timezone.
+pub fn parse_ics(contents: &str) -> Result<Vec<DateTime<FixedOffset>>, String> {
+    let unfolded = contents
+        .replace("\r\n", "\n")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+    let mut dates = Vec::new();
+    let mut in_event = false;
+    for line in unfolded.lines() {
+        match line {
+            "BEGIN:VEVENT" => in_event = true,
+            "END:VEVENT" => in_event = false,
+            _ if in_event => {
+                let Some((name, value)) = line.split_once(':') else {
+                    continue;
+                };
+                let mut params = name.split(';');
+                if params.next() != Some("DTSTART") {
+                    continue;
+                }
+                if params.any(|param| param.starts_with("TZID=")) {
+                    return Err(format!(
+                        "'{line}' is in a named timezone, which glaza can't convert, export the calendar in UTC instead"
+                    ));
+                }
+                dates.push(parse_ics_date(value)?);
+            },
+            _ => (),
+        }
+    }
+    if dates.is_empty() {
+        return Err("there are no events in the calendar".into());
+    }
+    dates.sort();
+    Ok(dates)
+}
+
+fn parse_ics_date(value: &str) -> Result<DateTime<FixedOffset>, String> {
+    if let Some(utc) = value.strip_suffix('Z') {
+        if let Ok(date) = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S") {
+            return Ok(date.and_utc().fixed_offset());
+        }
+    }
+    let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .or_else(|_| NaiveDate::parse_from_str(value, "%Y%m%d").map(|date| date.and_time(NaiveTime::MIN)))
+        .map_err(|_| format!("'{value}' is not an iCalendar date"))?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|date| date.fixed_offset())
+        .ok_or_else(|| format!("'{value}' doesn't exist in your local timezone"))
+}
+
+fn ics_date(date: DateTime<FixedOffset>) -> String {
+    date.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Splits `line` into lines of at most `MAX_LINE` bytes, the ones after the first starting with a
+/// space, without splitting any characters.
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for char in line.chars() {
+        if length + char.len_utf8() > MAX_LINE {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(char);
+        length += char.len_utf8();
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::DateTime;
+    use tempfile::TempDir;
+
+    use super::calendar;
+    use super::fold;
+    use super::parse_ics;
+    use super::MAX_LINE;
+    use crate::models::show::CurrentRepo;
+
+    #[test]
+    fn folded_lines_fit() {
+        let line = format!("SUMMARY:{}", "葬送のフリーレン ".repeat(10));
+        let folded = fold(&line);
+        assert!(folded.split("\r\n").all(|part| part.len() <= MAX_LINE));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+
+    #[test]
+    fn events_are_read_in_order() {
+        let ics = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nSUMMARY:ep2\r\nDTSTART:20240329T1\r\n 50000Z\r\nEND:VEVENT\r\nBEGIN:VEVENT\r\nDTSTART:20240322T150000Z\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        assert_eq!(parse_ics(ics).unwrap(), [
+            DateTime::parse_from_rfc3339("2024-03-22T15:00:00Z").unwrap(),
+            DateTime::parse_from_rfc3339("2024-03-29T15:00:00Z").unwrap(),
+        ]);
+        assert!(parse_ics("BEGIN:VEVENT\nDTSTART;TZID=Asia/Tokyo:20240322T150000\nEND:VEVENT").is_err());
+    }
+
+    #[test]
+    fn weekly_shows_export_the_episodes_left() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("current.yml");
+        fs::write(
+            &path,
+            "Frieren:\n  episode: 1\n  downloaded: 1\n  link: null\n  dlink: null\n  total: 3\n  schedule: !weekly\n    weekday: Thu\n    time: 18:00:00\n    utc_offset: +09:00\n    since: 2024-03-20\n",
+        )
+        .unwrap();
+        let current_model = CurrentRepo::try_from(path.as_path()).unwrap();
+        let ics = calendar(&current_model, 4);
+        assert!(ics.contains("UID:Frieren-ep2@glaza\r\n"));
+        assert!(ics.contains("DTSTART:20240328T090000Z\r\n"));
+        // and `schedule --ics` reads back the same air dates
+        assert_eq!(parse_ics(&ics).unwrap(), [
+            DateTime::parse_from_rfc3339("2024-03-28T18:00:00+09:00").unwrap(),
+            DateTime::parse_from_rfc3339("2024-04-04T18:00:00+09:00").unwrap(),
+        ]);
+    }
+}
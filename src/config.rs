@@ -0,0 +1,70 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::FixedOffset;
+use serde::Deserialize;
+
+use crate::models::schedule::utc_offset;
+use crate::models::watched::DateFormat;
+
+const CONFIG_DIR: &str = "glaza";
+const CONFIG_FILE: &str = "config.yml";
+
+/// Settings from `config.yml` in your config_directory/glaza.
+/// The file is optional, and so is every setting in it.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// How far into an episode (in percent) you have to get in mpv for `play` to mark it watched.
+    pub mpv_threshold: f64,
+    /// The git remote of the data directory that `sync` pulls from and pushes to.
+    pub remote: String,
+    /// How many snapshots of the data files to keep in the `backups` directory, 0 turns them off.
+    pub backups: usize,
+    /// The token `serve` wants for its JSON API and dashboard buttons, which are open to anyone
+    /// without one.
+    pub api_token: Option<String>,
+    /// How the dates of the watched list are written, `legacy` (yy.mm.dd) or `iso` (YYYY-MM-DD).
+    pub date_format: DateFormat,
+    /// The timezone that decides the day in the watched list, like `+09:00`. Your local one if
+    /// unset.
+    #[serde(with = "utc_offset")]
+    pub utc_offset: Option<FixedOffset>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            mpv_threshold: 85.0,
+            remote: "origin".into(),
+            backups: 20,
+            api_token: None,
+            date_format: DateFormat::default(),
+            utc_offset: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> Result<Self, String> {
+        let config_file = get_config_dir().join(CONFIG_FILE);
+        let Ok(contents) = fs::read_to_string(&config_file) else {
+            return Ok(Self::default());
+        };
+        if contents.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        serde_yaml::from_str(&contents)
+            .map_err(|err| format!("couldn't parse {}: {err}", config_file.display()))
+    }
+}
+
+/// `$GLAZA_CONFIG_DIR`, or the glaza directory in your config directory.
+pub fn get_config_dir() -> PathBuf {
+    if let Some(config_dir) = env::var_os("GLAZA_CONFIG_DIR") {
+        return config_dir.into();
+    }
+    let config_dir = dirs::config_dir().unwrap();
+    config_dir.join(CONFIG_DIR)
+}
@@ -1,20 +1,30 @@
+use std::env;
 use std::fs;
+use std::fs::File;
+use std::fs::OpenOptions;
 use std::path::Path;
 use std::path::PathBuf;
 
-use crate::sh;
-use crate::sh::is_git_init;
+use crate::git;
+use crate::git::is_git_init;
 
 const DATA_DIR: &str = "glaza";
-const CURRENT_FILE: &str = "current.yml";
-const WATCHED_FILE: &str = "watched.txt";
-const WATCH_LATER_FILE: &str = "watch-later.txt";
+pub const CURRENT_FILE: &str = "current.yml";
+pub const WATCHED_FILE: &str = "watched.txt";
+pub const WATCH_LATER_FILE: &str = "watch-later.txt";
+pub const LOG_FILE: &str = "episodes.txt";
+/// Locked by whatever is changing the data files, so that `glaza` and `glaza serve` take turns.
+pub const LOCK_FILE: &str = ".lock";
+/// Set for the hooks, that run while glaza holds the lock, so that the glaza they run doesn't
+/// wait for it forever.
+pub const LOCK_HELD_VAR: &str = "GLAZA_LOCK_HELD";
 
 #[derive(Debug)]
 pub struct DataFiles {
     pub current: PathBuf,
     pub watched: PathBuf,
     pub watch_later: PathBuf,
+    pub log: PathBuf,
     pub data_dir: PathBuf,
 }
 
@@ -25,16 +35,41 @@ impl DataFiles {
         let current = data_dir.join(CURRENT_FILE);
         let watched = data_dir.join(WATCHED_FILE);
         let watch_later = data_dir.join(WATCH_LATER_FILE);
+        let log = data_dir.join(LOG_FILE);
         Ok(Self {
             current,
             watched,
             watch_later,
+            log,
             data_dir,
         })
     }
+
+    /// Waits for anything else using the data files to be done, and keeps them to yourself until
+    /// the returned file is dropped.
+    /// Inside of a hook, the glaza that ran it already has them, so this doesn't lock anything.
+    pub fn lock(&self) -> Result<Option<File>, String> {
+        if env::var_os(LOCK_HELD_VAR).is_some() {
+            return Ok(None);
+        }
+        let lock_path = self.data_dir.join(LOCK_FILE);
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .map_err(|err| format!("couldn't open {}: {err}", lock_path.display()))?;
+        file.lock()
+            .map_err(|err| format!("couldn't lock {}: {err}", lock_path.display()))?;
+        Ok(Some(file))
+    }
 }
 
+/// `$GLAZA_DATA_DIR`, or the glaza directory in your data directory.
 fn get_data_dir() -> PathBuf {
+    if let Some(data_dir) = env::var_os("GLAZA_DATA_DIR") {
+        return data_dir.into();
+    }
     let data_dir = dirs::data_dir().unwrap();
     data_dir.join(DATA_DIR)
 }
@@ -47,7 +82,7 @@ fn init_data_dir(data_dir: &Path, git_init: bool) -> Result<(), String> {
         )
     })?;
     if git_init && !is_git_init(data_dir) {
-        sh::git_init(data_dir)?;
+        git::git_init(data_dir)?;
     }
     Ok(())
 }
@@ -0,0 +1,277 @@
+//! Checks the data files for mistakes that hand editing them tends to make, and fixes the ones
+//! that can be fixed without guessing.
+
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::fs;
+use std::io::ErrorKind;
+
+use url::Url;
+
+use crate::backup::snapshot;
+use crate::data::DataFiles;
+use crate::data::CURRENT_FILE;
+use crate::data::WATCHED_FILE;
+use crate::data::WATCH_LATER_FILE;
+use crate::git::git_add_commit;
+use crate::models::show::Shows;
+use crate::models::watched::parse_date;
+
+struct Problem {
+    file: &'static str,
+    line: Option<usize>,
+    column: Option<usize>,
+    message: String,
+    /// Whether `--fix` takes care of it.
+    fixable: bool,
+}
+
+impl Problem {
+    fn new(file: &'static str, line: Option<usize>, message: String) -> Self {
+        Self {
+            file,
+            line,
+            column: None,
+            message,
+            fixable: false,
+        }
+    }
+
+    fn fixable(mut self) -> Self {
+        self.fixable = true;
+        self
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file)?;
+        if let Some(line) = self.line {
+            write!(f, ":{line}")?;
+        }
+        if let Some(column) = self.column {
+            write!(f, ":{column}")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Reports every problem in the data files, and with `fix`, fixes the safe ones:
+/// blank lines, duplicate watch later entries, and watch later entries of shows you already
+/// started.
+/// With `git`, the fixes are committed.
+pub fn doctor(data: &DataFiles, fix: bool, git: bool) -> Result<(), String> {
+    // like the repos, a file that doesn't exist yet is an empty one
+    let read = |file_name: &str| match fs::read_to_string(data.data_dir.join(file_name)) {
+        Ok(contents) => Ok(contents),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(String::new()),
+        Err(_) => Err(format!("couldn't read {file_name}")),
+    };
+    let current = read(CURRENT_FILE)?;
+    let watched = read(WATCHED_FILE)?;
+    let watch_later = read(WATCH_LATER_FILE)?;
+
+    let (shows, mut problems) = check_current(&current);
+    let (fixed_watched, watched_problems) = check_watched(&watched);
+    problems.extend(watched_problems);
+    let (fixed_watch_later, watch_later_problems) = check_watch_later(&watch_later, shows.as_ref());
+    problems.extend(watch_later_problems);
+
+    for problem in problems.iter() {
+        println!("{problem}");
+    }
+    let mut changed = false;
+    if fix {
+        for (path, contents, fixed) in [
+            (&data.watched, &watched, fixed_watched),
+            (&data.watch_later, &watch_later, fixed_watch_later),
+        ] {
+            if *contents != fixed {
+                snapshot(path)?;
+                fs::write(path, fixed).map_err(|_| format!("couldn't write {}", path.display()))?;
+                changed = true;
+            }
+        }
+    }
+    if changed && git {
+        git_add_commit(&data.data_dir, "doctor fix".into())?;
+    }
+    let remaining = problems
+        .iter()
+        .filter(|problem| !(fix && problem.fixable))
+        .count();
+    let found = problems.len();
+    if found == 0 {
+        println!("no problems found");
+    } else if remaining == 0 {
+        println!("fixed all {found} problems");
+    } else if fix {
+        return Err(format!(
+            "fixed {} of {found} problems, the other {remaining} need fixing by hand",
+            found - remaining
+        ));
+    } else {
+        let fixable = problems.iter().filter(|problem| problem.fixable).count();
+        return Err(format!(
+            "found {found} problems, {fixable} of which `--fix` can fix"
+        ));
+    }
+    Ok(())
+}
+
+fn check_current(contents: &str) -> (Option<Shows>, Vec<Problem>) {
+    if contents.trim().is_empty() {
+        return (Some(Shows::new()), Vec::new());
+    }
+    let shows: Shows = match serde_yaml::from_str(contents) {
+        Ok(shows) => shows,
+        Err(err) => {
+            let location = err.location();
+            let problem = Problem {
+                line: location.as_ref().map(|location| location.line()),
+                column: location.as_ref().map(|location| location.column()),
+                ..Problem::new(CURRENT_FILE, None, err.to_string())
+            };
+            return (None, vec![problem]);
+        },
+    };
+    let mut problems = Vec::new();
+    let mut titles: Vec<&String> = shows.keys().collect();
+    titles.sort();
+    for title in titles {
+        let show = &shows[title];
+        let line = contents
+            .lines()
+            .position(|line| line.trim_end().strip_suffix(':') == Some(title))
+            .map(|index| index + 1);
+        for (kind, link) in [("link", &show.link), ("dlink", &show.dlink)] {
+            if let Some(link) = link {
+                if let Err(err) = Url::parse(link) {
+                    problems.push(Problem::new(
+                        CURRENT_FILE,
+                        line,
+                        format!("{title}: {kind} '{link}' is not a url: {err}"),
+                    ));
+                }
+            }
+        }
+        if show.downloaded < show.episode {
+            problems.push(Problem::new(
+                CURRENT_FILE,
+                line,
+                format!(
+                    "{title}: watched up to ep {}, but only downloaded up to ep {}",
+                    show.episode, show.downloaded
+                ),
+            ));
+        }
+    }
+    (Some(shows), problems)
+}
+
+/// Every line should be a `yy.mm.dd - Title` or a `YYYY-MM-DD - Title`.
+fn check_watched(contents: &str) -> (String, Vec<Problem>) {
+    let mut problems = Vec::new();
+    let mut fixed = String::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line_number = Some(index + 1);
+        if line.trim().is_empty() {
+            problems.push(Problem::new(WATCHED_FILE, line_number, "blank line".into()).fixable());
+            continue;
+        }
+        let well_formed = line
+            .split_once(" - ")
+            .is_some_and(|(date, title)| parse_date(date).is_some() && !title.trim().is_empty());
+        if !well_formed {
+            problems.push(Problem::new(
+                WATCHED_FILE,
+                line_number,
+                format!("'{line}' is in neither the 'yy.mm.dd - Title' nor the 'YYYY-MM-DD - Title' format"),
+            ));
+        }
+        fixed.push_str(line);
+        fixed.push('\n');
+    }
+    if !problems.iter().any(|problem| problem.fixable) {
+        fixed = contents.to_owned();
+    }
+    (fixed, problems)
+}
+
+fn check_watch_later(contents: &str, shows: Option<&Shows>) -> (String, Vec<Problem>) {
+    let mut problems = Vec::new();
+    let mut seen = HashSet::new();
+    let mut kept = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line_number = Some(index + 1);
+        let problem = if line.trim().is_empty() {
+            Some("blank line".to_owned())
+        } else if !seen.insert(line) {
+            Some(format!("'{line}' is already on an earlier line"))
+        } else if shows.is_some_and(|shows| shows.contains_key(line)) {
+            Some(format!(
+                "'{line}' is in {CURRENT_FILE} too, as you already started it"
+            ))
+        } else {
+            None
+        };
+        match problem {
+            Some(message) => problems.push(Problem::new(WATCH_LATER_FILE, line_number, message).fixable()),
+            None => kept.push(line),
+        }
+    }
+    if problems.is_empty() {
+        return (contents.to_owned(), problems);
+    }
+    (kept.join("\n"), problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::check_current;
+    use super::check_watch_later;
+    use super::check_watched;
+    use super::doctor;
+    use crate::data::DataFiles;
+
+    #[test]
+    fn current_errors_have_locations() {
+        let (shows, problems) =
+            check_current("Frieren:\n  episode: three\n  downloaded: 3\n  link: null\n  dlink: null\n");
+        assert!(shows.is_none());
+        assert_eq!(problems[0].line, Some(2));
+        let (_, problems) =
+            check_current("Frieren:\n  episode: 4\n  downloaded: 3\n  link: website.com\n  dlink: null\n");
+        assert_eq!(problems.len(), 2);
+    }
+
+    #[test]
+    fn safe_problems_get_fixed() {
+        let (shows, _) =
+            check_current("Frieren:\n  episode: 1\n  downloaded: 1\n  link: null\n  dlink: null\n");
+        let (fixed, problems) = check_watch_later("Mushishi\n\nMonster\nFrieren\nMushishi", shows.as_ref());
+        assert_eq!(fixed, "Mushishi\nMonster");
+        assert_eq!(problems.len(), 3);
+        let (fixed, problems) = check_watched("24.01.02 - Bleach\n\nBleach again\n");
+        assert_eq!(fixed, "24.01.02 - Bleach\nBleach again\n");
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].fixable && !problems[1].fixable);
+    }
+
+    #[test]
+    fn fresh_data_dir_is_healthy() {
+        let dir = TempDir::new().unwrap();
+        let data = DataFiles {
+            current: dir.path().join("current.yml"),
+            watched: dir.path().join("watched.txt"),
+            watch_later: dir.path().join("watch-later.txt"),
+            log: dir.path().join("episodes.txt"),
+            data_dir: dir.path().to_path_buf(),
+        };
+        assert!(doctor(&data, false, false).is_ok());
+        assert!(doctor(&data, true, false).is_ok());
+        assert!(!data.watched.exists());
+    }
+}
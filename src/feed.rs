@@ -0,0 +1,194 @@
+//! Atom and RSS feeds of the shows you finished and dropped, for publishing what you watch.
+//! The feed only changes when the watched list does, so it's fine to regenerate it on every
+//! commit.
+
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use url::form_urlencoded;
+
+use crate::format::escape_html;
+use crate::models::entry::Entry;
+use crate::models::entry::Status;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum FeedFormat {
+    Atom,
+    Rss,
+}
+
+/// A finished or dropped show, as it goes in the feed.
+struct Item<'a> {
+    entry: &'a Entry,
+    date: NaiveDate,
+}
+
+impl Item<'_> {
+    fn title(&self) -> String {
+        let entry = self.entry;
+        let mut title = match (entry.status, entry.episode) {
+            (Status::Dropped, Some(episode)) => format!("Dropped {} at ep {episode}", entry.title),
+            (Status::Dropped, None) => format!("Dropped {}", entry.title),
+            _ => format!("Finished {}", entry.title),
+        };
+        if let Some(rewatch) = entry.rewatch {
+            title.push_str(&format!(" (rewatch #{rewatch})"));
+        }
+        title
+    }
+
+    /// Stays the same as long as the line in the watched list does.
+    fn id(&self) -> String {
+        let title: String = form_urlencoded::byte_serialize(self.entry.title.as_bytes()).collect();
+        let mut id = format!(
+            "urn:glaza:{}:{}:{title}",
+            self.date.format("%Y-%m-%d"),
+            self.entry.status
+        );
+        if let Some(rewatch) = self.entry.rewatch {
+            id.push_str(&format!(":{rewatch}"));
+        }
+        id
+    }
+}
+
+/// Renders a feed of the `entries` of the watched list finished or dropped on or after `since`,
+/// newest first.
+/// `link` is where the feed gets published, if anywhere.
+pub fn feed(entries: &[Entry], format: FeedFormat, since: Option<NaiveDate>, link: Option<&str>) -> String {
+    let items: Vec<Item> = entries
+        .iter()
+        .rev()
+        .filter_map(|entry| {
+            Some(Item {
+                entry,
+                date: entry.date?,
+            })
+        })
+        .filter(|item| since.is_none_or(|since| item.date >= since))
+        .collect();
+    match format {
+        FeedFormat::Atom => atom(&items, link),
+        FeedFormat::Rss => rss(&items, link),
+    }
+}
+
+fn atom(items: &[Item], link: Option<&str>) -> String {
+    let updated = items.iter().map(|item| item.date).max().unwrap_or_default();
+    let mut feed = String::from(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">\n  <title>glaza</title>\n  <subtitle>Shows finished and dropped</subtitle>\n",
+    );
+    match link {
+        Some(link) => {
+            feed.push_str(&format!("  <id>{}</id>\n", escape_html(link)));
+            feed.push_str(&format!(
+                "  <link rel=\"self\" href=\"{}\"/>\n",
+                escape_html(link)
+            ));
+        },
+        None => feed.push_str("  <id>urn:glaza:watched</id>\n"),
+    }
+    feed.push_str(&format!("  <updated>{}</updated>\n", atom_date(updated)));
+    feed.push_str("  <author><name>glaza</name></author>\n");
+    for item in items {
+        feed.push_str(&format!(
+            "  <entry>\n    <id>{}</id>\n    <title>{}</title>\n    <updated>{}</updated>\n    <category term=\"{}\"/>\n    <content type=\"text\">{}</content>\n  </entry>\n",
+            escape_html(&item.id()),
+            escape_html(&item.title()),
+            atom_date(item.date),
+            item.entry.status,
+            escape_html(&item.entry.describe()),
+        ));
+    }
+    feed.push_str("</feed>\n");
+    feed
+}
+
+fn rss(items: &[Item], link: Option<&str>) -> String {
+    let mut feed = String::from(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<rss version=\"2.0\">\n  <channel>\n    <title>glaza</title>\n    <description>Shows finished and dropped</description>\n",
+    );
+    feed.push_str(&format!(
+        "    <link>{}</link>\n",
+        escape_html(link.unwrap_or_default())
+    ));
+    if let Some(latest) = items.iter().map(|item| item.date).max() {
+        feed.push_str(&format!(
+            "    <lastBuildDate>{}</lastBuildDate>\n",
+            rss_date(latest)
+        ));
+    }
+    for item in items {
+        feed.push_str(&format!(
+            "    <item>\n      <guid isPermaLink=\"false\">{}</guid>\n      <title>{}</title>\n      <pubDate>{}</pubDate>\n      <category>{}</category>\n      <description>{}</description>\n    </item>\n",
+            escape_html(&item.id()),
+            escape_html(&item.title()),
+            rss_date(item.date),
+            item.entry.status,
+            escape_html(&item.entry.describe()),
+        ));
+    }
+    feed.push_str("  </channel>\n</rss>\n");
+    feed
+}
+
+/// The watched list only has days, so everything happens at midnight UTC.
+fn atom_date(date: NaiveDate) -> String {
+    date.format("%Y-%m-%dT00:00:00Z").to_string()
+}
+
+fn rss_date(date: NaiveDate) -> String {
+    date.format("%a, %d %b %Y 00:00:00 +0000").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::feed;
+    use super::FeedFormat;
+    use crate::models::entry::Entry;
+    use crate::models::entry::Status;
+
+    #[test]
+    fn newest_first_since_the_date() {
+        let entries = [
+            Entry {
+                title: "Bleach".into(),
+                status: Status::Completed,
+                episode: None,
+                date: NaiveDate::from_ymd_opt(2023, 1, 2),
+                rewatch: None,
+            },
+            Entry {
+                title: "Mushishi & Monster".into(),
+                status: Status::Dropped,
+                episode: Some(4),
+                date: NaiveDate::from_ymd_opt(2023, 9, 3),
+                rewatch: None,
+            },
+            Entry {
+                title: "Frieren".into(),
+                status: Status::Completed,
+                episode: None,
+                date: NaiveDate::from_ymd_opt(2024, 3, 22),
+                rewatch: None,
+            },
+        ];
+        let since = NaiveDate::from_ymd_opt(2023, 2, 1);
+        let atom = feed(&entries, FeedFormat::Atom, since, None);
+        assert!(!atom.contains("Bleach"));
+        assert!(
+            atom.find("Finished Frieren").unwrap()
+                < atom.find("Dropped Mushishi &amp; Monster at ep 4").unwrap()
+        );
+        assert!(atom.contains("<updated>2024-03-22T00:00:00Z</updated>"));
+        let rss = feed(
+            &entries,
+            FeedFormat::Rss,
+            None,
+            Some("https://example.com/feed.xml"),
+        );
+        assert!(rss.contains("<pubDate>Sun, 03 Sep 2023 00:00:00 +0000</pubDate>"));
+        assert!(rss.contains("<category>dropped</category>"));
+    }
+}
@@ -0,0 +1,235 @@
+//! The `--format` template language of the list commands.
+//!
+//! - `{name}` is replaced with the value of the `name` field.
+//! - `{name:<N}` and `{name:>N}` pad the value to at least `N` columns, aligning it to the left
+//!   or to the right. Without `N` (`{name:<}`), the value is padded to the longest value of the
+//!   field across every line, so that the field lines up.
+//! - `[...]` is only printed if every field inside of it has a value.
+//! - `\t`, `\n`, `\\`, `\{`, `\}`, `\[` and `\]` are escapes.
+
+use std::collections::HashMap;
+
+use unicode_width::UnicodeWidthStr;
+
+/// The values of the fields of a single line. Fields without a value are left out.
+pub type Fields = HashMap<&'static str, String>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Align {
+    Left,
+    Right,
+}
+
+#[derive(Debug, PartialEq)]
+enum Part {
+    Literal(String),
+    Field {
+        name: &'static str,
+        /// How to align the value, and to how many characters, if at all.
+        /// A width of `None` means the longest value of the field.
+        padding: Option<(Align, Option<usize>)>,
+    },
+    Optional(Vec<Part>),
+}
+
+#[derive(Debug)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    /// Parses `template`, only allowing the fields in `known`.
+    pub fn parse(template: &str, known: &[&'static str]) -> Result<Self, String> {
+        let mut chars = template.chars();
+        let mut stack: Vec<Vec<Part>> = vec![Vec::new()];
+        let mut literal = String::new();
+        let flush = |literal: &mut String, parts: &mut Vec<Part>| {
+            if !literal.is_empty() {
+                parts.push(Part::Literal(std::mem::take(literal)));
+            }
+        };
+        while let Some(char) = chars.next() {
+            match char {
+                '\\' => match chars.next() {
+                    Some('t') => literal.push('\t'),
+                    Some('n') => literal.push('\n'),
+                    Some(escaped @ ('\\' | '{' | '}' | '[' | ']')) => literal.push(escaped),
+                    Some(other) => return Err(format!("unknown escape '\\{other}' in the format")),
+                    None => return Err("the format ends with a lone '\\'".into()),
+                },
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(char) => placeholder.push(char),
+                            None => return Err(format!("'{{{placeholder}' is never closed in the format")),
+                        }
+                    }
+                    let part = parse_placeholder(&placeholder, known)?;
+                    let parts = stack.last_mut().unwrap();
+                    flush(&mut literal, parts);
+                    parts.push(part);
+                },
+                '}' => return Err("unmatched '}' in the format, escape it as '\\}'".into()),
+                '[' => {
+                    flush(&mut literal, stack.last_mut().unwrap());
+                    stack.push(Vec::new());
+                },
+                ']' => {
+                    if stack.len() == 1 {
+                        return Err("unmatched ']' in the format, escape it as '\\]'".into());
+                    }
+                    let mut optional = stack.pop().unwrap();
+                    flush(&mut literal, &mut optional);
+                    stack.last_mut().unwrap().push(Part::Optional(optional));
+                },
+                _ => literal.push(char),
+            }
+        }
+        if stack.len() > 1 {
+            return Err("a '[' is never closed in the format".into());
+        }
+        let mut parts = stack.pop().unwrap();
+        flush(&mut literal, &mut parts);
+        Ok(Self { parts })
+    }
+
+    /// Renders a line for each of `rows`, lining up the fields that are padded to the longest
+    /// value.
+    pub fn render(&self, rows: &[Fields]) -> Vec<String> {
+        let mut widths: HashMap<&str, usize> = HashMap::new();
+        for row in rows {
+            for (name, value) in row {
+                let width = widths.entry(name).or_default();
+                *width = (*width).max(value.width());
+            }
+        }
+        rows.iter()
+            .map(|row| {
+                let mut line = String::new();
+                render_parts(&self.parts, row, &widths, &mut line);
+                line
+            })
+            .collect()
+    }
+}
+
+fn parse_placeholder(placeholder: &str, known: &[&'static str]) -> Result<Part, String> {
+    let (name, spec) = match placeholder.split_once(':') {
+        Some((name, spec)) => (name, Some(spec)),
+        None => (placeholder, None),
+    };
+    let Some(name) = known.iter().find(|known| **known == name) else {
+        return Err(format!(
+            "unknown field '{name}' in the format, the fields are: {}",
+            known.join(", ")
+        ));
+    };
+    let padding = match spec {
+        None => None,
+        Some(spec) => {
+            let align = match spec.chars().next() {
+                Some('<') => Align::Left,
+                Some('>') => Align::Right,
+                _ => {
+                    return Err(format!(
+                        "'{{{placeholder}}}' should be '{{{name}:<N}}' or '{{{name}:>N}}'"
+                    ))
+                },
+            };
+            let width = &spec[1..];
+            let width = match width {
+                "" => None,
+                width => Some(
+                    width
+                        .parse()
+                        .map_err(|_| format!("'{width}' in '{{{placeholder}}}' is not a width"))?,
+                ),
+            };
+            Some((align, width))
+        },
+    };
+    Ok(Part::Field { name, padding })
+}
+
+fn render_parts(parts: &[Part], row: &Fields, widths: &HashMap<&str, usize>, line: &mut String) {
+    for part in parts {
+        match part {
+            Part::Literal(literal) => line.push_str(literal),
+            Part::Field { name, padding } => {
+                let value = row.get(name).map(String::as_str).unwrap_or_default();
+                let Some((align, width)) = padding else {
+                    line.push_str(value);
+                    continue;
+                };
+                let width = width.unwrap_or_else(|| widths.get(name).copied().unwrap_or_default());
+                let padding = " ".repeat(width.saturating_sub(value.width()));
+                match align {
+                    Align::Left => {
+                        line.push_str(value);
+                        line.push_str(&padding);
+                    },
+                    Align::Right => {
+                        line.push_str(&padding);
+                        line.push_str(value);
+                    },
+                }
+            },
+            Part::Optional(parts) => {
+                if has_values(parts, row) {
+                    render_parts(parts, row, widths, line);
+                }
+            },
+        }
+    }
+}
+
+fn has_values(parts: &[Part], row: &Fields) -> bool {
+    parts.iter().all(|part| match part {
+        Part::Literal(_) => true,
+        Part::Field { name, .. } => row.get(name).is_some_and(|value| !value.is_empty()),
+        Part::Optional(_) => true,
+    })
+}
+
+/// Escapes `text` for html and xml, both inside of elements and of quoted attributes.
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Fields;
+    use super::Template;
+
+    const KNOWN: &[&str] = &["title", "episode", "total"];
+
+    fn row(fields: &[(&'static str, &str)]) -> Fields {
+        fields
+            .iter()
+            .map(|(name, value)| (*name, value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn fields_align_and_sections_are_optional() {
+        let template = Template::parse("{title:<} {episode:>3}[/{total}]\\t.", KNOWN).unwrap();
+        let rows = [
+            row(&[("title", "Frieren"), ("episode", "4"), ("total", "28")]),
+            row(&[("title", "Mushishi"), ("episode", "12")]),
+        ];
+        assert_eq!(template.render(&rows), ["Frieren    4/28\t.", "Mushishi  12\t."]);
+    }
+
+    #[test]
+    fn mistakes_are_errors() {
+        assert!(Template::parse("{name}", KNOWN).is_err());
+        assert!(Template::parse("[{title}", KNOWN).is_err());
+        assert!(Template::parse("{title:^3}", KNOWN).is_err());
+        assert!(Template::parse("\\[{title}\\]", KNOWN).is_ok());
+    }
+}
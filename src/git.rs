@@ -0,0 +1,549 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use chrono::DateTime;
+use chrono::Local;
+use chrono::TimeZone;
+use clap::ValueEnum;
+use git2::build::CheckoutBuilder;
+use git2::AnnotatedCommit;
+use git2::Cred;
+use git2::CredentialType;
+use git2::ErrorCode;
+use git2::FetchOptions;
+use git2::IndexAddOption;
+use git2::PushOptions;
+use git2::RemoteCallbacks;
+use git2::Repository;
+use git2::Signature;
+use git2::Sort;
+use git2::Status;
+
+use crate::backup::BACKUPS_DIR;
+use crate::data::LOCK_FILE;
+use crate::merge::DataFile;
+
+const ATTRIBUTES_FILE: &str = ".gitattributes";
+
+fn open(data_dir: &Path) -> Result<Repository, String> {
+    Repository::open(data_dir).map_err(|err| {
+        format!(
+            "couldn't open the git repository in {}: {}",
+            data_dir.display(),
+            err.message()
+        )
+    })
+}
+
+fn git_error(action: &'static str) -> impl Fn(git2::Error) -> String {
+    move |err| format!("couldn't {action}: {}", err.message())
+}
+
+/// Like `git`, prefers `$GIT_AUTHOR_NAME` and `$GIT_AUTHOR_EMAIL` over the git config.
+fn signature(repo: &Repository) -> Result<Signature<'static>, String> {
+    if let (Ok(name), Ok(email)) = (env::var("GIT_AUTHOR_NAME"), env::var("GIT_AUTHOR_EMAIL")) {
+        return Signature::now(&name, &email).map_err(git_error("use the author from the environment"));
+    }
+    repo.signature().map_err(|err| {
+        format!(
+            "couldn't figure out who to commit as, set user.name and user.email in your git config: {}",
+            err.message()
+        )
+    })
+}
+
+/// The full name of the branch HEAD points to, even if it has no commits yet.
+fn head_branch(repo: &Repository) -> Result<String, String> {
+    repo.find_reference("HEAD")
+        .ok()
+        .and_then(|head| {
+            head.symbolic_target()
+                .ok()
+                .flatten()
+                .map(|target| target.to_owned())
+        })
+        .ok_or_else(|| "HEAD of the data directory doesn't point to a branch".into())
+}
+
+pub fn git_add_commit(working_dir: &Path, message: String) -> Result<(), String> {
+    let repo = open(working_dir)?;
+    ignore_local_files(&repo)?;
+    let mut index = repo.index().map_err(git_error("read the git index"))?;
+    index
+        .add_all(["*"], IndexAddOption::DEFAULT, None)
+        .map_err(git_error("git add"))?;
+    index.update_all(["*"], None).map_err(git_error("git add"))?;
+    index.write().map_err(git_error("write the git index"))?;
+    let tree_id = index.write_tree().map_err(git_error("write the git tree"))?;
+    let tree = repo.find_tree(tree_id).map_err(git_error("find the git tree"))?;
+    let parent = match repo.head() {
+        Ok(head) => Some(head.peel_to_commit().map_err(git_error("find the HEAD commit"))?),
+        Err(err) if err.code() == ErrorCode::UnbornBranch => None,
+        Err(err) => return Err(git_error("find HEAD")(err)),
+    };
+    if parent.as_ref().is_some_and(|parent| parent.tree_id() == tree_id) {
+        return Err("couldn't git commit: nothing changed in the data directory".into());
+    }
+    let signature = signature(&repo)?;
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        &message,
+        &tree,
+        parent.as_ref().into_iter().collect::<Vec<_>>().as_slice(),
+    )
+    .map_err(git_error("git commit"))?;
+    Ok(())
+}
+
+pub fn is_git_init(data_dir: &Path) -> bool {
+    data_dir.join(".git").exists()
+}
+
+pub fn git_init(data_dir: &Path) -> Result<(), String> {
+    let repo = Repository::init(data_dir).map_err(|err| {
+        format!(
+            "couldn't git init the data directory in {}: {}",
+            data_dir.display(),
+            err.message()
+        )
+    })?;
+    ignore_local_files(&repo)?;
+    register_merge_drivers(&repo)
+}
+
+/// Keeps the backups and the lock file out of git, without committing a `.gitignore` for them.
+fn ignore_local_files(repo: &Repository) -> Result<(), String> {
+    let exclude_path = repo.path().join("info").join("exclude");
+    let mut exclude = fs::read_to_string(&exclude_path).unwrap_or_default();
+    let patterns = [format!("/{BACKUPS_DIR}/"), format!("/{LOCK_FILE}")];
+    let missing: Vec<&String> = patterns
+        .iter()
+        .filter(|pattern| !exclude.lines().any(|line| line == pattern.as_str()))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    if !exclude.is_empty() && !exclude.ends_with('\n') {
+        exclude.push('\n');
+    }
+    for pattern in missing {
+        exclude.push_str(pattern);
+        exclude.push('\n');
+    }
+    if let Some(info_dir) = exclude_path.parent() {
+        fs::create_dir_all(info_dir).map_err(|_| "couldn't create the .git/info directory")?;
+    }
+    fs::write(&exclude_path, exclude).map_err(|_| "couldn't write .git/info/exclude".into())
+}
+
+/// Makes git merge the data files with `glaza merge-driver`, rather than line by line.
+fn register_merge_drivers(repo: &Repository) -> Result<(), String> {
+    let mut config = repo.config().map_err(git_error("read the git config"))?;
+    let workdir = repo
+        .workdir()
+        .ok_or("the data directory git repository is bare")?;
+    let attributes_path = workdir.join(ATTRIBUTES_FILE);
+    let mut attributes = fs::read_to_string(&attributes_path).unwrap_or_default();
+    for data_file in DataFile::ALL {
+        let driver = data_file.driver_name();
+        let kind = data_file.to_possible_value().unwrap();
+        config
+            .set_str(
+                &format!("merge.{driver}.driver"),
+                &format!("glaza merge-driver {} %O %A %B", kind.get_name()),
+            )
+            .map_err(git_error("register the merge driver"))?;
+        let attribute = format!("{} merge={driver}", data_file.file_name());
+        if !attributes.lines().any(|line| line == attribute) {
+            if !attributes.is_empty() && !attributes.ends_with('\n') {
+                attributes.push('\n');
+            }
+            attributes.push_str(&attribute);
+            attributes.push('\n');
+        }
+    }
+    fs::write(&attributes_path, attributes).map_err(|_| format!("couldn't write {ATTRIBUTES_FILE}"))
+}
+
+/// Resolves the conflicts in the data files with the same merge that `glaza merge-driver` does,
+/// because libgit2 doesn't use merge drivers by itself.
+/// `local_is_ours` says which side of the conflict is the one from this machine, so that it
+/// takes priority.
+fn resolve_conflicts(repo: &Repository, local_is_ours: bool) -> Result<(), String> {
+    let mut index = repo.index().map_err(git_error("read the git index"))?;
+    let conflicts: Vec<_> = index
+        .conflicts()
+        .map_err(git_error("read the conflicts"))?
+        .collect::<Result<_, _>>()
+        .map_err(git_error("read the conflicts"))?;
+    let workdir = repo
+        .workdir()
+        .ok_or("the data directory git repository is bare")?;
+    let read_blob = |entry: Option<&git2::IndexEntry>| -> Result<String, String> {
+        let Some(entry) = entry else {
+            return Ok(String::new());
+        };
+        let blob = repo
+            .find_blob(entry.id)
+            .map_err(git_error("read a conflicting file"))?;
+        Ok(String::from_utf8_lossy(blob.content()).into_owned())
+    };
+    for conflict in conflicts {
+        let entry = conflict
+            .our
+            .as_ref()
+            .or(conflict.their.as_ref())
+            .or(conflict.ancestor.as_ref())
+            .ok_or("a conflict without any sides")?;
+        let path = String::from_utf8_lossy(&entry.path).into_owned();
+        let Some(data_file) = DataFile::from_file_name(&path) else {
+            return Err(format!(
+                "'{path}' has conflicts that glaza doesn't know how to merge"
+            ));
+        };
+        let base = read_blob(conflict.ancestor.as_ref())?;
+        let (local, remote) = if local_is_ours {
+            (
+                read_blob(conflict.our.as_ref())?,
+                read_blob(conflict.their.as_ref())?,
+            )
+        } else {
+            (
+                read_blob(conflict.their.as_ref())?,
+                read_blob(conflict.our.as_ref())?,
+            )
+        };
+        let merged = data_file.merge(&base, &local, &remote)?;
+        fs::write(workdir.join(&path), merged).map_err(|_| format!("couldn't write the merged {path}"))?;
+        index
+            .add_path(Path::new(&path))
+            .map_err(git_error("add the merged file"))?;
+    }
+    index.write().map_err(git_error("write the git index"))
+}
+
+/// The time and message of every commit reachable from HEAD, oldest first.
+pub fn git_log(data_dir: &Path) -> Result<Vec<(DateTime<Local>, String)>, String> {
+    let repo = open(data_dir)?;
+    let mut revwalk = repo.revwalk().map_err(git_error("walk the git log"))?;
+    if repo.head().is_err() {
+        return Ok(Vec::new());
+    }
+    revwalk.push_head().map_err(git_error("walk the git log"))?;
+    revwalk
+        .set_sorting(Sort::TOPOLOGICAL | Sort::TIME | Sort::REVERSE)
+        .map_err(git_error("walk the git log"))?;
+    let mut commits = Vec::new();
+    for oid in revwalk {
+        let oid = oid.map_err(git_error("walk the git log"))?;
+        let commit = repo.find_commit(oid).map_err(git_error("read a commit"))?;
+        let time = Local
+            .timestamp_opt(commit.time().seconds(), 0)
+            .single()
+            .unwrap_or_default();
+        commits.push((time, String::from_utf8_lossy(commit.message_bytes()).into_owned()));
+    }
+    Ok(commits)
+}
+
+fn remote_callbacks<'a>(repo: &Repository) -> Result<RemoteCallbacks<'a>, String> {
+    let config = repo
+        .config()
+        .map_err(|err| format!("couldn't read the git config: {}", err.message()))?;
+    let mut callbacks = RemoteCallbacks::new();
+    let mut attempts = 0;
+    callbacks.credentials(move |url, username, allowed| {
+        // libgit2 keeps asking for as long as the credentials we give it get rejected
+        attempts += 1;
+        if attempts > 3 {
+            return Err(git2::Error::from_str("authentication failed"));
+        }
+        if allowed.contains(CredentialType::SSH_KEY) {
+            Cred::ssh_key_from_agent(username.unwrap_or("git"))
+        } else if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            Cred::credential_helper(&config, url, username)
+        } else {
+            Cred::default()
+        }
+    });
+    Ok(callbacks)
+}
+
+/// Pulls (rebasing your local commits on top of the remote ones), and then pushes the current
+/// branch of the data directory to `remote_name`.
+pub fn sync(data_dir: &Path, remote_name: &str) -> Result<(), String> {
+    let repo = open(data_dir)?;
+    ignore_local_files(&repo)?;
+    register_merge_drivers(&repo)?;
+    let branch_ref = head_branch(&repo)?;
+    let branch = branch_ref.trim_start_matches("refs/heads/");
+    let mut remote = repo
+        .find_remote(remote_name)
+        .map_err(|_| format!("the data directory has no git remote named '{remote_name}'"))?;
+    let upstream_ref = format!("refs/remotes/{remote_name}/{branch}");
+
+    let mut fetch_options = FetchOptions::new();
+    fetch_options.remote_callbacks(remote_callbacks(&repo)?);
+    remote
+        .fetch(
+            &[format!("+{branch_ref}:{upstream_ref}")],
+            Some(&mut fetch_options),
+            None,
+        )
+        .map_err(git_error("fetch"))?;
+
+    if let Ok(upstream) = repo.find_reference(&upstream_ref) {
+        let upstream = repo
+            .reference_to_annotated_commit(&upstream)
+            .map_err(git_error("read the fetched branch"))?;
+        pull(&repo, &branch_ref, &upstream)?;
+    }
+
+    if repo.head().is_err() {
+        // nothing to push, neither here nor on the remote
+        return Ok(());
+    }
+    let mut rejection = None;
+    {
+        let mut callbacks = remote_callbacks(&repo)?;
+        callbacks.push_update_reference(|_, status| {
+            rejection = status.map(|status| status.to_owned());
+            Ok(())
+        });
+        let mut push_options = PushOptions::new();
+        push_options.remote_callbacks(callbacks);
+        remote
+            .push(&[format!("{branch_ref}:{branch_ref}")], Some(&mut push_options))
+            .map_err(git_error("push"))?;
+    }
+    match rejection {
+        Some(status) => Err(format!("the remote rejected the push: {status}")),
+        None => Ok(()),
+    }
+}
+
+fn pull(repo: &Repository, branch_ref: &str, upstream: &AnnotatedCommit) -> Result<(), String> {
+    let (analysis, _) = repo
+        .merge_analysis(&[upstream])
+        .map_err(git_error("compare with the remote"))?;
+    if analysis.is_up_to_date() {
+        return Ok(());
+    }
+    if analysis.is_unborn() {
+        // a data directory that was never committed to can only take the remote as it is, but
+        // it shouldn't overwrite any data in the process
+        let statuses = repo.statuses(None).map_err(git_error("get the git status"))?;
+        for entry in statuses.iter() {
+            let has_data = entry.status().contains(Status::WT_NEW)
+                && entry.path().is_ok_and(|path| path != ATTRIBUTES_FILE)
+                && entry
+                    .path()
+                    .ok()
+                    .zip(repo.workdir())
+                    .and_then(|(path, workdir)| fs::metadata(workdir.join(path)).ok())
+                    .is_some_and(|metadata| metadata.len() > 0);
+            if has_data {
+                return Err(format!(
+                    "'{}' has uncommitted data, commit it before syncing",
+                    entry.path().unwrap_or_default()
+                ));
+            }
+        }
+        repo.reference(branch_ref, upstream.id(), true, "glaza sync: initial pull")
+            .map_err(git_error("create the local branch"))?;
+        return repo
+            .checkout_head(Some(CheckoutBuilder::new().force()))
+            .map_err(git_error("check out the remote branch"));
+    }
+    if analysis.is_fast_forward() {
+        // the working tree has to be updated before the branch, for git to know which files
+        // are changed by the pull, rather than by you
+        let target = repo
+            .find_object(upstream.id(), None)
+            .map_err(git_error("find the pulled commit"))?;
+        repo.checkout_tree(&target, Some(CheckoutBuilder::new().safe()))
+            .map_err(git_error("check out the pulled changes"))?;
+        let mut branch = repo
+            .find_reference(branch_ref)
+            .map_err(git_error("find the local branch"))?;
+        branch
+            .set_target(upstream.id(), "glaza sync: fast-forward")
+            .map_err(git_error("fast-forward"))?;
+        return Ok(());
+    }
+    rebase(repo, upstream)
+}
+
+fn rebase(repo: &Repository, upstream: &AnnotatedCommit) -> Result<(), String> {
+    let head = repo.head().map_err(git_error("find HEAD"))?;
+    let local = repo
+        .reference_to_annotated_commit(&head)
+        .map_err(git_error("read HEAD"))?;
+    let signature = signature(repo)?;
+    let mut rebase = repo
+        .rebase(Some(&local), Some(upstream), None, None)
+        .map_err(git_error("start rebasing"))?;
+    while let Some(operation) = rebase.next() {
+        operation.map_err(git_error("rebase"))?;
+        let index = repo.index().map_err(git_error("read the git index"))?;
+        if index.has_conflicts() {
+            // while rebasing, the remote commits are "ours", and the local commit being applied
+            // on top of them is "theirs"
+            if let Err(err) = resolve_conflicts(repo, false) {
+                rebase.abort().ok();
+                return Err(format!(
+                    "{err}\nyour local changes conflict with the remote ones, resolve that with git yourself"
+                ));
+            }
+        }
+        match rebase.commit(None, &signature, None) {
+            Ok(_) => (),
+            // the remote already has this exact change
+            Err(err) if err.code() == ErrorCode::Applied => (),
+            Err(err) => {
+                rebase.abort().ok();
+                return Err(git_error("commit while rebasing")(err));
+            },
+        }
+    }
+    rebase
+        .finish(Some(&signature))
+        .map_err(git_error("finish rebasing"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use git2::Repository;
+    use tempfile::TempDir;
+
+    use super::git_add_commit;
+    use super::git_init;
+    use super::sync;
+
+    fn machine(remote: &Path) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        git_init(dir.path()).unwrap();
+        let repo = Repository::open(dir.path()).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "glaza").unwrap();
+        config.set_str("user.email", "glaza@localhost").unwrap();
+        repo.remote("origin", remote.to_str().unwrap()).unwrap();
+        dir
+    }
+
+    #[test]
+    fn commit_without_changes_fails() {
+        let remote = TempDir::new().unwrap();
+        let laptop = machine(remote.path());
+        fs::write(laptop.path().join("watch-later.txt"), "Frieren").unwrap();
+        git_add_commit(laptop.path(), "wl add -> Frieren".into()).unwrap();
+        assert!(git_add_commit(laptop.path(), "wl add -> Frieren".into()).is_err());
+    }
+
+    #[test]
+    fn sync_through_bare_remote() {
+        let remote = TempDir::new().unwrap();
+        Repository::init_bare(remote.path()).unwrap();
+        let laptop = machine(remote.path());
+        let desktop = machine(remote.path());
+
+        fs::write(laptop.path().join("watch-later.txt"), "Frieren\n").unwrap();
+        git_add_commit(laptop.path(), "wl add -> Frieren".into()).unwrap();
+        sync(laptop.path(), "origin").unwrap();
+
+        sync(desktop.path(), "origin").unwrap();
+        assert_eq!(
+            fs::read_to_string(desktop.path().join("watch-later.txt")).unwrap(),
+            "Frieren\n"
+        );
+
+        // both machines change different files, so the second sync has to rebase
+        fs::write(desktop.path().join("watched.txt"), "23.09.03 - Mushishi\n").unwrap();
+        git_add_commit(desktop.path(), "finish -> Mushishi".into()).unwrap();
+        fs::write(laptop.path().join("current.yml"), "Frieren:\n  episode: 1\n").unwrap();
+        git_add_commit(laptop.path(), "start -> Frieren".into()).unwrap();
+        sync(desktop.path(), "origin").unwrap();
+        sync(laptop.path(), "origin").unwrap();
+        sync(desktop.path(), "origin").unwrap();
+
+        for machine in [&laptop, &desktop] {
+            assert!(machine.path().join("watched.txt").exists());
+            assert!(machine.path().join("current.yml").exists());
+            let repo = Repository::open(machine.path()).unwrap();
+            let head = repo.head().unwrap().peel_to_commit().unwrap();
+            assert_eq!(head.message().unwrap(), "start -> Frieren");
+        }
+    }
+
+    #[test]
+    fn sync_merges_conflicting_progress() {
+        let remote = TempDir::new().unwrap();
+        Repository::init_bare(remote.path()).unwrap();
+        let laptop = machine(remote.path());
+        let desktop = machine(remote.path());
+        let show = |episode: u32, downloaded: u32| {
+            format!(
+                "Frieren:\n  episode: {episode}\n  downloaded: {downloaded}\n  link: null\n  dlink: null\n"
+            )
+        };
+
+        fs::write(laptop.path().join("current.yml"), show(1, 1)).unwrap();
+        git_add_commit(laptop.path(), "start -> Frieren".into()).unwrap();
+        sync(laptop.path(), "origin").unwrap();
+        sync(desktop.path(), "origin").unwrap();
+
+        fs::write(laptop.path().join("current.yml"), show(3, 1)).unwrap();
+        git_add_commit(laptop.path(), "watch ep3 -> Frieren".into()).unwrap();
+        fs::write(desktop.path().join("current.yml"), show(2, 5)).unwrap();
+        git_add_commit(desktop.path(), "download ep5 -> Frieren".into()).unwrap();
+        sync(laptop.path(), "origin").unwrap();
+        sync(desktop.path(), "origin").unwrap();
+
+        assert_eq!(
+            fs::read_to_string(desktop.path().join("current.yml")).unwrap(),
+            show(3, 5)
+        );
+    }
+
+    #[test]
+    fn sync_merges_both_episode_logs() {
+        let remote = TempDir::new().unwrap();
+        Repository::init_bare(remote.path()).unwrap();
+        let laptop = machine(remote.path());
+        let desktop = machine(remote.path());
+        let first = "2024-03-01T10:00:00Z - ep0 -> ep1 - Frieren\n";
+        let laptop_line = "2024-03-03T10:00:00Z - ep1 -> ep2 - Frieren\n";
+        let desktop_line = "2024-03-02T10:00:00Z - ep3 -> ep4 - Mushishi\n";
+
+        fs::write(laptop.path().join("episodes.txt"), first).unwrap();
+        git_add_commit(laptop.path(), "watch ep1 -> Frieren".into()).unwrap();
+        sync(laptop.path(), "origin").unwrap();
+        sync(desktop.path(), "origin").unwrap();
+
+        fs::write(
+            laptop.path().join("episodes.txt"),
+            format!("{first}{laptop_line}"),
+        )
+        .unwrap();
+        git_add_commit(laptop.path(), "watch ep2 -> Frieren".into()).unwrap();
+        fs::write(
+            desktop.path().join("episodes.txt"),
+            format!("{first}{desktop_line}"),
+        )
+        .unwrap();
+        git_add_commit(desktop.path(), "watch ep4 -> Mushishi".into()).unwrap();
+        sync(laptop.path(), "origin").unwrap();
+        sync(desktop.path(), "origin").unwrap();
+
+        assert_eq!(
+            fs::read_to_string(desktop.path().join("episodes.txt")).unwrap(),
+            format!("{first}{desktop_line}{laptop_line}")
+        );
+    }
+}
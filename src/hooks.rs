@@ -0,0 +1,129 @@
+use std::fmt::Display;
+use std::io::Write;
+use std::process::Command;
+use std::process::Stdio;
+
+use serde::Serialize;
+
+use crate::config::get_config_dir;
+use crate::data::LOCK_HELD_VAR;
+use crate::models::show::Show;
+
+const HOOKS_DIR: &str = "hooks";
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Stage {
+    Pre,
+    Post,
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pre => write!(f, "pre"),
+            Self::Post => write!(f, "post"),
+        }
+    }
+}
+
+/// What a hook gets told about the action that triggered it.
+/// For `download` events, the episodes are the downloaded episodes.
+#[derive(Debug, Default, Serialize)]
+pub struct Event {
+    pub event: &'static str,
+    pub show: String,
+    pub old_episode: Option<u32>,
+    pub new_episode: Option<u32>,
+    /// The downloaded episode an `episode` event sets as well, like a `PATCH` of `serve` does.
+    pub new_downloaded: Option<u32>,
+    pub link: Option<String>,
+    pub dlink: Option<String>,
+    pub outcome: Option<&'static str>,
+}
+
+impl Event {
+    pub fn new(event: &'static str, show: impl ToString) -> Self {
+        Self {
+            event,
+            show: show.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Fills in the current episode and links of `show`.
+    pub fn with_show(mut self, show: &Show) -> Self {
+        self.old_episode = Some(show.episode);
+        self.link = show.link.clone();
+        self.dlink = show.dlink.clone();
+        self
+    }
+}
+
+/// Runs the `<stage>-<event>` hook from the hooks directory in your config_directory/glaza, if
+/// it exists.
+/// The event is passed to the hook both as `GLAZA_*` environment variables, and as json on stdin.
+/// The data directory stays locked while the hook runs, and the glaza commands it runs know that.
+/// A `pre` hook exiting unsuccessfully is an error, that should abort the action.
+/// A `post` hook failing is only warned about, as the action already happened by then.
+pub fn run_hook(stage: Stage, event: &Event) -> Result<(), String> {
+    match (run(stage, event), stage) {
+        (Err(err), Stage::Post) => {
+            eprintln!("warning: {err}");
+            Ok(())
+        },
+        (result, _) => result,
+    }
+}
+
+fn run(stage: Stage, event: &Event) -> Result<(), String> {
+    let hook_name = format!("{stage}-{}", event.event);
+    let hook = get_config_dir().join(HOOKS_DIR).join(&hook_name);
+    if !hook.is_file() {
+        return Ok(());
+    }
+    let json = serde_json::to_string(event).map_err(|_| "couldn't serialize the hook event into json")?;
+    let mut command = Command::new(&hook);
+    command
+        .env("GLAZA_HOOK", &hook_name)
+        .env("GLAZA_EVENT", event.event)
+        .env("GLAZA_SHOW", &event.show)
+        .env(LOCK_HELD_VAR, "1")
+        .stdin(Stdio::piped());
+    let optional_vars = [
+        (
+            "GLAZA_OLD_EPISODE",
+            event.old_episode.map(|episode| episode.to_string()),
+        ),
+        (
+            "GLAZA_NEW_EPISODE",
+            event.new_episode.map(|episode| episode.to_string()),
+        ),
+        (
+            "GLAZA_NEW_DOWNLOADED",
+            event.new_downloaded.map(|episode| episode.to_string()),
+        ),
+        ("GLAZA_LINK", event.link.clone()),
+        ("GLAZA_DLINK", event.dlink.clone()),
+        ("GLAZA_OUTCOME", event.outcome.map(|outcome| outcome.to_owned())),
+    ];
+    for (name, value) in optional_vars {
+        if let Some(value) = value {
+            command.env(name, value);
+        }
+    }
+    let mut child = command
+        .spawn()
+        .map_err(|_| format!("couldn't execute the {hook_name} hook at {}", hook.display()))?;
+    // the hook not reading its stdin is fine, so a failed write is as well
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(json.as_bytes()).ok();
+    }
+    let status = child
+        .wait()
+        .map_err(|_| format!("couldn't wait for the {hook_name} hook to exit"))?;
+    match (status.success(), stage) {
+        (true, _) => Ok(()),
+        (false, Stage::Pre) => Err(format!("{hook_name} hook exited unsuccessfully, aborting")),
+        (false, Stage::Post) => Err(format!("{hook_name} hook exited unsuccessfully")),
+    }
+}
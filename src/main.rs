@@ -1,20 +1,70 @@
 use std::error::Error;
+use std::fs;
+use std::path::Path;
 use std::process::ExitCode;
 
+use args::BackupCommands;
 use args::UserCommands;
+use args::WatchedCommands;
+use calendar::calendar;
+use calendar::parse_ics;
+use chrono::Datelike;
+use chrono::Utc;
 use clap::Parser;
+use config::Config;
 use data::DataFiles;
+use doctor::doctor;
+use feed::feed;
+use git::git_add_commit;
+use git::is_git_init;
+use git::sync;
+use hooks::run_hook;
+use hooks::Event;
+use hooks::Stage;
+use merge::DataFile;
+use models::entry::locate;
+use models::entry::search;
+use models::entry::Entry;
+use models::entry::Status;
+use models::entry::ENTRY_FIELDS;
+use models::episode_files::compile_pattern;
+use models::history::HistoryRepo;
+use models::log::LogRepo;
+use models::schedule::Schedule;
 use models::show::CurrentRepo;
+use models::show::ListOptions;
+use models::show::SHOW_FIELDS;
+use models::watched;
+use models::watched::Day;
 use models::watched::WatchedRepo;
 use models::wl::WlRepo;
-use sh::git_add_commit;
+use ops::Ending;
+use ops::Source;
+use report::report;
+use serve::serve;
+use sh::confirm;
+use sh::play;
 
 use crate::args::Args;
 
 mod args;
+mod backup;
+mod calendar;
+mod config;
 mod data;
+mod doctor;
+mod feed;
+mod format;
+mod git;
+mod hooks;
+mod merge;
 mod models;
+mod mpv;
+mod ops;
+mod report;
+mod serve;
 mod sh;
+mod table;
 
 fn main() -> ExitCode {
     match _main() {
@@ -28,10 +78,43 @@ fn main() -> ExitCode {
 
 fn _main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
+    if let UserCommands::MergeDriver {
+        kind,
+        base,
+        ours,
+        theirs,
+    } = &args.action
+    {
+        // git runs merge drivers in the middle of a merge, so this shouldn't touch the data files
+        return merge_driver(*kind, base, ours, theirs);
+    }
+    let config = Config::load()?;
+    backup::set_keep(config.backups);
+    watched::set_dates(config.date_format, config.utc_offset);
     let data = DataFiles::build(args.git)?;
-    let current_model = CurrentRepo::try_from(data.current.as_path())?;
+    // `serve` locks the data files for each request instead, and the commands that only read
+    // them don't wait, so that hooks can run them
+    let mut lock = match args.action {
+        UserCommands::Serve { .. } => None,
+        ref action if action.is_read_only() => None,
+        _ => data.lock()?,
+    };
+    if let UserCommands::Doctor { fix } = args.action {
+        // the point is to look at files that might not load
+        if !fix {
+            return Ok(doctor(&data, fix, args.git)?);
+        }
+        let event = Event::new("doctor", "");
+        run_hook(Stage::Pre, &event)?;
+        // it fails when problems remain, but that doesn't undo the ones it fixed
+        let result = doctor(&data, fix, args.git);
+        run_hook(Stage::Post, &event)?;
+        return Ok(result?);
+    }
+    let mut current_model = CurrentRepo::try_from(data.current.as_path())?;
     let mut watched_model = WatchedRepo::try_from(data.watched.as_path())?;
     let mut wl_model = WlRepo::try_from(data.watch_later.as_path())?;
+    let mut log_model = LogRepo::try_from(data.log.as_path())?;
     match args.action {
         UserCommands::Watch { show, web } => {
             let show = current_model.normalize_show_pattern(&show)?;
@@ -51,6 +134,57 @@ fn _main() -> Result<(), Box<dyn Error>> {
             }
             Ok(())
         },
+        UserCommands::Play { show, mark } => {
+            let show = current_model.normalize_show_pattern(&show)?;
+            let file = current_model.get_next_episode_file(&show)?;
+            // let `serve` get on with things while the episode plays, and catch up with it after
+            drop(lock.take());
+            let progress = play(&file, current_model.get_resume(&show))?;
+            let _lock = data.lock()?;
+            current_model = CurrentRepo::try_from(data.current.as_path())?;
+            log_model = LogRepo::try_from(data.log.as_path())?;
+            let show = current_model
+                .match_show_pattern(show.as_str())
+                .map_err(|_| format!("'{show}' stopped being watched while it played"))?;
+            let previous_episode = current_model.get_episode(&show);
+            let episode = previous_episode + 1;
+            let mut event = Event::new("episode", &show).with_show(current_model.get_show(&show));
+            event.new_episode = Some(episode);
+            match progress {
+                Some(progress) if progress.percent < config.mpv_threshold => {
+                    let position = progress.position.round();
+                    event.event = "pause";
+                    run_hook(Stage::Pre, &event)?;
+                    current_model.change_resume(&show, Some(position))?;
+                    eprintln!(
+                        "stopped at {:.0}% of episode {episode}, `play` will continue from {position}s",
+                        progress.percent
+                    );
+                    if args.git {
+                        git_add_commit(
+                            &data.data_dir,
+                            format!("pause ep{episode} at {position}s -> {show}"),
+                        )?
+                    }
+                    run_hook(Stage::Post, &event)?;
+                    return Ok(());
+                },
+                Some(_) => (),
+                None => {
+                    if !mark && !confirm(&format!("mark episode {episode} of '{show}' as watched?")) {
+                        return Ok(());
+                    }
+                },
+            }
+            Ok(ops::watch_episode(
+                &data,
+                args.git,
+                current_model,
+                &mut log_model,
+                &show,
+                episode,
+            )?)
+        },
         UserCommands::Plink { show, web } => {
             let show = current_model.normalize_show_pattern(&show)?;
             if web {
@@ -69,125 +203,597 @@ fn _main() -> Result<(), Box<dyn Error>> {
             }
             Ok(())
         },
-        UserCommands::Finish { show, grab, fresh } => {
-            let show: String = if grab {
-                let show = wl_model.normalize_show_pattern(&show)?;
-                wl_model.remove(&show)?;
-                show.into()
-            } else if !fresh {
-                let show = current_model.normalize_show_pattern(&show)?;
-                current_model.remove(&show)?;
-                show.into()
+        UserCommands::Finish {
+            ref show,
+            grab,
+            fresh,
+            date,
+        }
+        | UserCommands::Drop {
+            ref show,
+            grab,
+            fresh,
+            date,
+        } => {
+            let ending = if matches!(args.action, UserCommands::Finish { .. }) {
+                Ending::Finish(date)
             } else {
-                show
+                Ending::Drop(date)
             };
-            watched_model.finish(&show)?;
-            if args.git {
-                git_add_commit(&data.data_dir, format!("finish -> {show}"))?;
-            }
-            Ok(())
-        },
-        UserCommands::Drop { show, grab, fresh } => {
-            let latest_episode;
-            let show: String = if grab {
-                let show = wl_model.normalize_show_pattern(&show)?;
-                latest_episode = current_model.get_episode(&show);
-                wl_model.remove(&show)?;
-                show.into()
-            } else if !fresh {
-                let show = current_model.normalize_show_pattern(&show)?;
-                latest_episode = current_model.get_episode(&show);
-                current_model.remove(&show)?;
-                show.into()
+            let source = if grab {
+                Source::Grabbed(wl_model.normalize_show_pattern(show)?)
+            } else if fresh {
+                Source::Fresh(show.to_owned())
             } else {
-                latest_episode = 0;
-                show
+                Source::Current(current_model.normalize_show_pattern(show)?)
             };
-            watched_model.drop(latest_episode, &show)?;
-            if args.git {
-                git_add_commit(&data.data_dir, format!("drop at {latest_episode} -> {show}"))?;
-            }
-            Ok(())
+            Ok(ops::end(
+                &data,
+                args.git,
+                ending,
+                current_model,
+                &mut wl_model,
+                &mut watched_model,
+                source,
+            )?)
         },
         UserCommands::Start {
             show,
             link,
             dlink,
             grab,
+            date,
         } => {
-            let show: String = if grab {
-                let show = wl_model.normalize_show_pattern(&show)?;
-                wl_model.remove(&show)?;
-                show.into()
+            let added = match date {
+                Some(day) => day.start()?,
+                None => Utc::now(),
+            };
+            let grabbed = if grab {
+                Some(wl_model.normalize_show_pattern(&show)?)
             } else {
-                show
+                None
             };
-            current_model.new_show(&show, link.as_ref(), dlink.as_ref())?;
+            let show = grabbed.as_ref().map(|show| show.to_string()).unwrap_or(show);
+            let entries = all_entries(&current_model, &wl_model, &watched_model)?;
+            // the grabbed show is expected to be in watch later
+            let expected: &[Status] = if grab { &[Status::Planned] } else { &[] };
+            warn_existing(&show, &entries, expected);
+            let mut event = Event::new("start", &show);
+            event.link = link.clone();
+            event.dlink = dlink.clone();
+            run_hook(Stage::Pre, &event)?;
+            if let Some(grabbed) = grabbed {
+                wl_model.remove(&grabbed)?;
+            }
+            let rewatch = watched_model.next_rewatch(&show)?;
+            if let Some(rewatch) = rewatch {
+                println!("you finished {show} before, so this is rewatch #{rewatch}");
+            }
+            current_model.new_show(&show, link.as_ref(), dlink.as_ref(), rewatch, added)?;
             if args.git {
                 git_add_commit(&data.data_dir, format!("start -> {show}"))?;
             }
+            run_hook(Stage::Post, &event)?;
+            Ok(())
+        },
+        UserCommands::Shows {
+            links,
+            on_hold,
+            sort,
+            reverse,
+            has_backlog,
+            no_link,
+            stale,
+            format,
+            columns,
+            color,
+        } => {
+            let options = ListOptions {
+                format: format.template(SHOW_FIELDS)?,
+                columns,
+                color: color.should_color(),
+                links,
+                on_hold,
+                sort,
+                reverse,
+                has_backlog,
+                no_link,
+                stale,
+            };
+            Ok(current_model.list(&options, &log_model)?)
+        },
+        UserCommands::Find { pattern } => {
+            let entries = all_entries(&current_model, &wl_model, &watched_model)?;
+            let found = search(&entries, &pattern);
+            if found.is_empty() {
+                return Err(format!("found no show matching '{pattern}'").into());
+            }
+            for entry in found {
+                println!("{} — {}: {}", entry.title, entry.file_name(), entry.describe());
+            }
             Ok(())
         },
-        UserCommands::Shows { links } => Ok(current_model.list(links)?),
+        UserCommands::Total { show, total } => {
+            let show = current_model.normalize_show_pattern(&show)?;
+            let event = Event::new("total", &show).with_show(current_model.get_show(&show));
+            run_hook(Stage::Pre, &event)?;
+            current_model.change_total(&show, total)?;
+            if args.git {
+                let message = match total {
+                    Some(total) => format!("total {total} -> {show}"),
+                    None => format!("clear total -> {show}"),
+                };
+                git_add_commit(&data.data_dir, message)?;
+            }
+            run_hook(Stage::Post, &event)?;
+            Ok(())
+        },
+        UserCommands::Hold { show } => change_status(
+            Status::OnHold,
+            &show,
+            current_model,
+            &wl_model,
+            &watched_model,
+            &data.data_dir,
+            args.git,
+        ),
+        UserCommands::Resume { show } => change_status(
+            Status::Watching,
+            &show,
+            current_model,
+            &wl_model,
+            &watched_model,
+            &data.data_dir,
+            args.git,
+        ),
         UserCommands::Remove { show } => {
             let show = current_model.normalize_show_pattern(&show)?;
+            let event = Event::new("remove", &show).with_show(current_model.get_show(&show));
+            run_hook(Stage::Pre, &event)?;
             current_model.remove(&show)?;
             if args.git {
                 git_add_commit(&data.data_dir, format!("remove -> {show}"))?;
             }
+            run_hook(Stage::Post, &event)?;
             Ok(())
         },
         UserCommands::Episode { show, episode } => {
             let show = current_model.normalize_show_pattern(&show)?;
-            current_model.change_episode(&show, episode)?;
-            if args.git {
-                git_add_commit(&data.data_dir, format!("watch ep{episode} -> {show}"))?
-            }
-            Ok(())
+            Ok(ops::watch_episode(
+                &data,
+                args.git,
+                current_model,
+                &mut log_model,
+                &show,
+                episode,
+            )?)
         },
         UserCommands::Download { show, episode } => {
             let show = current_model.normalize_show_pattern(&show)?;
-            current_model.change_downloaded(&show, episode)?;
-            if args.git {
-                git_add_commit(&data.data_dir, format!("download ep{episode} -> {show}"))?;
-            }
-            Ok(())
+            Ok(ops::download_episode(
+                &data,
+                args.git,
+                current_model,
+                &mut log_model,
+                &show,
+                episode,
+            )?)
         },
         UserCommands::Link { show, link } => {
             let show = current_model.normalize_show_pattern(&show)?;
+            let mut event = Event::new("link", &show).with_show(current_model.get_show(&show));
+            event.link = Some(link.clone());
+            run_hook(Stage::Pre, &event)?;
             current_model.change_link(&show, &link, false)?;
             if args.git {
                 git_add_commit(&data.data_dir, format!("update link -> {show} -> {link}"))?
             }
+            run_hook(Stage::Post, &event)?;
             Ok(())
         },
         UserCommands::Dlink { show, link } => {
             let show = current_model.normalize_show_pattern(&show)?;
+            let mut event = Event::new("dlink", &show).with_show(current_model.get_show(&show));
+            event.dlink = Some(link.clone());
+            run_hook(Stage::Pre, &event)?;
             current_model.change_link(&show, &link, true)?;
             if args.git {
                 git_add_commit(&data.data_dir, format!("update dlink -> {show} -> {link}"))?
             }
+            run_hook(Stage::Post, &event)?;
             Ok(())
         },
-        UserCommands::Add { show } => {
-            wl_model.add(&show)?;
+        UserCommands::Schedule {
+            show,
+            weekday,
+            time,
+            utc_offset,
+            since,
+            first,
+            mut dates,
+            ics,
+            clear,
+        } => {
+            let show = current_model.normalize_show_pattern(&show)?;
+            if let Some(ics) = ics {
+                let contents = fs::read_to_string(&ics)
+                    .map_err(|err| format!("couldn't read {}: {err}", ics.display()))?;
+                dates = parse_ics(&contents)?;
+            }
+            // clap guarantees that if we got the weekday, we also got the time and the since date
+            let schedule = match (weekday, time, since) {
+                (Some(weekday), Some(time), Some(since)) => Some(Schedule::Weekly {
+                    weekday,
+                    time,
+                    utc_offset,
+                    since,
+                    first_episode: first,
+                }),
+                _ if clear => None,
+                _ => {
+                    dates.sort();
+                    Some(Schedule::Dates(dates))
+                },
+            };
+            let event = Event::new("schedule", &show).with_show(current_model.get_show(&show));
+            run_hook(Stage::Pre, &event)?;
+            current_model.change_schedule(&show, schedule)?;
+            if args.git {
+                let verb = if clear { "clear schedule" } else { "schedule" };
+                git_add_commit(&data.data_dir, format!("{verb} -> {show}"))?;
+            }
+            run_hook(Stage::Post, &event)?;
+            Ok(())
+        },
+        UserCommands::Dir { show, dir, pattern } => {
+            let show = current_model.normalize_show_pattern(&show)?;
+            compile_pattern(&pattern)?;
+            let dir = dir
+                .canonicalize()
+                .map_err(|_| format!("{} doesn't exist", dir.display()))?;
+            let event = Event::new("dir", &show).with_show(current_model.get_show(&show));
+            run_hook(Stage::Pre, &event)?;
+            current_model.change_download_dir(&show, dir, pattern)?;
+            if args.git {
+                git_add_commit(&data.data_dir, format!("update dir -> {show}"))?;
+            }
+            run_hook(Stage::Post, &event)?;
+            Ok(())
+        },
+        UserCommands::Scan { show, dry_run } => {
+            let show = match show {
+                Some(show) => Some(current_model.normalize_show_pattern(&show)?),
+                None => None,
+            };
+            let changes = current_model.scan(show.as_ref())?;
+            if changes.is_empty() {
+                eprintln!("no downloaded episodes changed");
+                return Ok(());
+            }
+            let mut messages = Vec::new();
+            let mut events = Vec::new();
+            for (show, old, new) in changes.iter() {
+                println!("{show} — dn{old} -> dn{new}");
+                messages.push(format!("download ep{new} -> {show}"));
+                let mut event = Event::new("download", show).with_show(current_model.get_show(show));
+                event.old_episode = Some(*old);
+                event.new_episode = Some(*new);
+                events.push(event);
+            }
+            if dry_run {
+                return Ok(());
+            }
+            for event in events.iter() {
+                run_hook(Stage::Pre, event)?;
+            }
+            current_model.apply_scan(&changes)?;
+            if args.git {
+                git_add_commit(&data.data_dir, messages.join("\n"))?;
+            }
+            for event in events.iter() {
+                run_hook(Stage::Post, event)?;
+            }
+            Ok(())
+        },
+        UserCommands::Calendar { weeks } => {
+            print!("{}", calendar(&current_model, weeks));
+            Ok(())
+        },
+        UserCommands::Due => {
+            let mut any_due = false;
+            for (show, aired) in current_model.get_aired() {
+                let episode = current_model.get_episode(&show);
+                let downloaded = current_model.get_downloaded(&show);
+                let mut news = Vec::new();
+                if aired > episode {
+                    let new = aired - episode;
+                    let plural = if new == 1 { "" } else { "s" };
+                    news.push(format!("{new} new episode{plural} since ep {episode}"));
+                }
+                if aired > downloaded {
+                    news.push(format!(
+                        "{} to download since dn {downloaded}",
+                        aired - downloaded
+                    ));
+                }
+                if !news.is_empty() {
+                    println!("{show} — {}", news.join(", "));
+                    any_due = true;
+                }
+            }
+            if !any_due {
+                eprintln!("no new episodes of your scheduled shows");
+            }
+            Ok(())
+        },
+        UserCommands::Rename {
+            show,
+            new_title,
+            everywhere,
+        } => {
+            let show = current_model.normalize_show_pattern(&show)?;
+            let new_title = new_title.trim();
+            if new_title.is_empty() {
+                return Err("the new title is empty".into());
+            }
+            if new_title == show.as_str() {
+                return Err(format!("'{show}' is already called that").into());
+            }
+            // check the watch later list before anything gets renamed
+            let wl_titles: Vec<String> = wl_model.entries().into_iter().map(|entry| entry.title).collect();
+            if everywhere
+                && wl_titles.contains(&show.to_string())
+                && wl_titles.iter().any(|title| title == new_title)
+            {
+                return Err(format!("watch later list already contains: '{new_title}'").into());
+            }
+            if log_model.has_title(new_title) {
+                return Err(format!("the episode log already has episodes of '{new_title}'").into());
+            }
+            let event = Event::new("rename", &show).with_show(current_model.get_show(&show));
+            run_hook(Stage::Pre, &event)?;
+            current_model.rename(&show, new_title)?;
+            // the log is what tells when you last watched it, so it always follows
+            log_model.rename(show.as_str(), new_title)?;
+            if everywhere {
+                if wl_model.rename(show.as_str(), new_title)? {
+                    println!("renamed it in your watch later list");
+                }
+                match watched_model.rename(show.as_str(), new_title)? {
+                    0 => (),
+                    1 => println!("renamed it in your watched list"),
+                    renamed => println!("renamed it {renamed} times in your watched list"),
+                }
+            }
             if args.git {
-                git_add_commit(&data.data_dir, format!("wl add -> {}", show))?;
+                git_add_commit(&data.data_dir, format!("rename {show} -> {new_title}"))?;
             }
+            run_hook(Stage::Post, &event)?;
             Ok(())
         },
+        UserCommands::Add { show } => {
+            let entries = all_entries(&current_model, &wl_model, &watched_model)?;
+            warn_existing(&show, &entries, &[]);
+            Ok(ops::add_to_watch_later(&data, args.git, &mut wl_model, &show)?)
+        },
         UserCommands::Discard { show } => {
             let show = wl_model.normalize_show_pattern(&show)?;
+            let event = Event::new("discard", &show);
+            run_hook(Stage::Pre, &event)?;
             wl_model.remove(&show)?;
             if args.git {
                 git_add_commit(&data.data_dir, format!("wl remove -> {}", show))?;
             }
+            run_hook(Stage::Post, &event)?;
+            Ok(())
+        },
+        UserCommands::Wl { format } => {
+            wl_model.list(format.template(ENTRY_FIELDS)?.as_ref());
+            Ok(())
+        },
+        UserCommands::Feed { format, since, link } => {
+            print!(
+                "{}",
+                feed(&watched_model.entries()?, format, since, link.as_deref())
+            );
+            Ok(())
+        },
+        UserCommands::Report { year, format } => {
+            let year = year.unwrap_or_else(|| watched::today().year());
+            print!(
+                "{}",
+                report(year, format, &watched_model.entries()?, log_model.entries())
+            );
+            Ok(())
+        },
+        UserCommands::Watched { action: None, format } => {
+            Ok(watched_model.read(format.template(ENTRY_FIELDS)?.as_ref())?)
+        },
+        UserCommands::Watched {
+            action:
+                Some(WatchedCommands::Edit {
+                    show,
+                    date,
+                    title,
+                    outcome,
+                    episode,
+                }),
+            ..
+        } => {
+            let show = watched_model.normalize_show_pattern(&show)?;
+            let title = title.map(|title| title.trim().to_owned());
+            if title.as_ref().is_some_and(|title| title.is_empty()) {
+                return Err("the new title is empty".into());
+            }
+            let event = Event::new("watched-edit", &show);
+            run_hook(Stage::Pre, &event)?;
+            watched_model.edit(&show, date.map(Day::date), title.as_deref(), outcome, episode)?;
+            if args.git {
+                git_add_commit(&data.data_dir, format!("watched edit -> {show}"))?;
+            }
+            run_hook(Stage::Post, &event)?;
+            Ok(())
+        },
+        UserCommands::Watched {
+            action: Some(WatchedCommands::Rm { show }),
+            ..
+        } => {
+            let show = watched_model.normalize_show_pattern(&show)?;
+            let event = Event::new("watched-rm", &show);
+            run_hook(Stage::Pre, &event)?;
+            watched_model.remove(&show)?;
+            if args.git {
+                git_add_commit(&data.data_dir, format!("watched rm -> {show}"))?;
+            }
+            run_hook(Stage::Post, &event)?;
             Ok(())
         },
-        UserCommands::Wl => {
-            wl_model.list();
+        UserCommands::Sync { remote } => {
+            if !is_git_init(&data.data_dir) {
+                return Err(
+                    "the data directory is not a git repository, use the `--git` flag to make it one".into(),
+                );
+            }
+            let event = Event::new("sync", "");
+            run_hook(Stage::Pre, &event)?;
+            sync(&data.data_dir, remote.as_ref().unwrap_or(&config.remote))?;
+            run_hook(Stage::Post, &event)?;
             Ok(())
         },
-        UserCommands::Watched => Ok(watched_model.read()?),
+        UserCommands::History { show } => {
+            let history_model = HistoryRepo::try_from(data.data_dir.as_path())?;
+            let show = match show {
+                Some(show) => Some(history_model.normalize_show_pattern(&show)?),
+                None => None,
+            };
+            Ok(history_model.print(show.as_ref())?)
+        },
+        UserCommands::Serve { bind } => Ok(serve(&data, &bind, args.git, config.api_token.as_deref())?),
+        UserCommands::Backup { action } => match action {
+            BackupCommands::List => {
+                let snapshots = backup::list(&data.data_dir);
+                if snapshots.is_empty() {
+                    println!("there are no backups yet");
+                }
+                for snapshot in snapshots {
+                    println!("{} — {}", snapshot.id, snapshot.files().join(", "));
+                }
+                Ok(())
+            },
+            BackupCommands::Restore { id } => {
+                let snapshot = backup::find(&data.data_dir, &id)?;
+                let event = Event::new("restore", "");
+                run_hook(Stage::Pre, &event)?;
+                backup::restore(&data.data_dir, &snapshot)?;
+                if args.git {
+                    git_add_commit(&data.data_dir, format!("restore backup {}", snapshot.id))?;
+                }
+                run_hook(Stage::Post, &event)?;
+                Ok(())
+            },
+            BackupCommands::Diff { id } => {
+                let snapshot = backup::find(&data.data_dir, &id)?;
+                print!("{}", backup::diff(&data.data_dir, &snapshot));
+                Ok(())
+            },
+        },
+        UserCommands::MergeDriver { .. } | UserCommands::Doctor { .. } => {
+            unreachable!("handled before loading the data files")
+        },
+        UserCommands::Log { show, since } => {
+            let show = match show {
+                Some(show) => Some(log_model.normalize_show_pattern(&show)?),
+                None => None,
+            };
+            Ok(log_model.print(show.as_ref(), since)?)
+        },
+    }
+}
+
+fn merge_driver(kind: DataFile, base: &Path, ours: &Path, theirs: &Path) -> Result<(), Box<dyn Error>> {
+    let read =
+        |path: &Path| fs::read_to_string(path).map_err(|_| format!("couldn't read {}", path.display()));
+    let merged = kind.merge(&read(base)?, &read(ours)?, &read(theirs)?)?;
+    fs::write(ours, merged)
+        .map_err(|_| format!("couldn't write the merge result into {}", ours.display()))?;
+    Ok(())
+}
+
+/// Puts a show on hold, or takes it off hold.
+fn change_status(
+    status: Status,
+    pattern: &str,
+    current_model: CurrentRepo,
+    wl_model: &WlRepo,
+    watched_model: &WatchedRepo,
+    data_dir: &Path,
+    git: bool,
+) -> Result<(), Box<dyn Error>> {
+    let event_name = match status {
+        Status::OnHold => "hold",
+        _ => "resume",
+    };
+    let show = match current_model.normalize_show_pattern(pattern) {
+        Ok(show) => show,
+        Err(err) => return Err(explain_not_current(err, pattern, wl_model, watched_model)?.into()),
+    };
+    if current_model.get_show(&show).status == status {
+        return Err(format!("{show} is already {status}").into());
+    }
+    let event = Event::new(event_name, &show).with_show(current_model.get_show(&show));
+    run_hook(Stage::Pre, &event)?;
+    current_model.change_status(&show, status)?;
+    if git {
+        git_add_commit(data_dir, format!("{event_name} -> {show}"))?;
     }
+    run_hook(Stage::Post, &event)?;
+    Ok(())
+}
+
+/// Every show in every data file: your watched list first, oldest first, then watch later, and
+/// then the shows you're watching.
+fn all_entries(
+    current_model: &CurrentRepo,
+    wl_model: &WlRepo,
+    watched_model: &WatchedRepo,
+) -> Result<Vec<Entry>, String> {
+    let mut entries = watched_model.entries()?;
+    entries.extend(wl_model.entries());
+    entries.extend(current_model.entries());
+    Ok(entries)
+}
+
+/// Warns about every entry titled exactly `title` (ignoring case), except for the ones with a
+/// status in `expected`.
+fn warn_existing(title: &str, entries: &[Entry], expected: &[Status]) {
+    for entry in search(entries, title) {
+        if entry.title.to_lowercase() == title.to_lowercase() && !expected.contains(&entry.status) {
+            eprintln!(
+                "warning: {} is already in {}: {}",
+                entry.title,
+                entry.file_name(),
+                entry.describe()
+            );
+        }
+    }
+}
+
+/// When a show couldn't be found among the ones you're watching, says where it is instead, if
+/// it's in watch later or your watched list.
+fn explain_not_current(
+    err: String,
+    pattern: &str,
+    wl_model: &WlRepo,
+    watched_model: &WatchedRepo,
+) -> Result<String, String> {
+    let mut entries = watched_model.entries()?;
+    entries.extend(wl_model.entries());
+    Ok(match locate(&entries, pattern) {
+        Ok(entry) => format!(
+            "{} is {}, not something you're watching",
+            entry.title,
+            entry.describe()
+        ),
+        Err(_) => err,
+    })
 }
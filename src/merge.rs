@@ -0,0 +1,189 @@
+//! Three-way merging of the data files, for when the same data directory was changed on two
+//! machines.
+
+use std::collections::HashSet;
+
+use clap::ValueEnum;
+use serde_yaml::Mapping;
+use serde_yaml::Value;
+
+use crate::data::CURRENT_FILE;
+use crate::data::LOG_FILE;
+use crate::data::WATCHED_FILE;
+use crate::data::WATCH_LATER_FILE;
+use crate::models::log::LogEntry;
+use crate::models::show::shows_to_yaml;
+use crate::models::show::Show;
+use crate::models::show::Shows;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum DataFile {
+    Current,
+    Watched,
+    Wl,
+    Log,
+}
+
+impl DataFile {
+    pub const ALL: [Self; 4] = [Self::Current, Self::Watched, Self::Wl, Self::Log];
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Self::Current => CURRENT_FILE,
+            Self::Watched => WATCHED_FILE,
+            Self::Wl => WATCH_LATER_FILE,
+            Self::Log => LOG_FILE,
+        }
+    }
+
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|data_file| data_file.file_name() == file_name)
+    }
+
+    /// The name of the git merge driver for this file.
+    pub fn driver_name(self) -> String {
+        format!("glaza-{}", self.to_possible_value().unwrap().get_name())
+    }
+
+    /// Merges `ours` and `theirs`, that both came from `base`.
+    pub fn merge(self, base: &str, ours: &str, theirs: &str) -> Result<String, String> {
+        match self {
+            Self::Current => merge_current(base, ours, theirs),
+            Self::Watched | Self::Wl => Ok(merge_lines(base, ours, theirs)),
+            Self::Log => Ok(merge_log(base, ours, theirs)),
+        }
+    }
+}
+
+fn parse_current(contents: &str, side: &str) -> Result<Shows, String> {
+    if contents.trim().is_empty() {
+        return Ok(Shows::new());
+    }
+    serde_yaml::from_str(contents)
+        .map_err(|err| format!("couldn't parse the {side} version of {CURRENT_FILE}: {err}"))
+}
+
+/// Shows added on either side are kept, and shows removed on either side are removed.
+/// Shows changed on both sides take the highest episode and downloaded episode of the two, the
+/// latest time either side updated them, and each of the rest of their properties from whichever
+/// side changed it (`ours`, if both did).
+fn merge_current(base: &str, ours: &str, theirs: &str) -> Result<String, String> {
+    let base = parse_current(base, "common")?;
+    let mut ours = parse_current(ours, "our")?;
+    let mut theirs = parse_current(theirs, "their")?;
+    let mut titles: Vec<String> = ours.keys().chain(theirs.keys()).cloned().collect();
+    titles.sort();
+    titles.dedup();
+    let mut merged = Shows::new();
+    for title in titles {
+        let show = match (base.get(&title), ours.remove(&title), theirs.remove(&title)) {
+            (_, Some(our_show), Some(their_show)) => merge_show(base.get(&title), our_show, their_show)?,
+            (None, Some(show), None) | (None, None, Some(show)) => show,
+            // it was there before, so one of the sides removed it
+            _ => continue,
+        };
+        merged.insert(title, show);
+    }
+    Ok(shows_to_yaml(merged)?)
+}
+
+fn merge_show(base: Option<&Show>, ours: Show, theirs: Show) -> Result<Show, String> {
+    let to_mapping = |show: &Show| match serde_yaml::to_value(show) {
+        Ok(Value::Mapping(mapping)) => Ok(mapping),
+        _ => Err("couldn't serialize a show into yaml"),
+    };
+    let base_properties = base.map(to_mapping).transpose()?.unwrap_or_default();
+    let our_properties = to_mapping(&ours)?;
+    let their_properties = to_mapping(&theirs)?;
+    let mut merged_properties = Mapping::new();
+    for key in our_properties.keys().chain(their_properties.keys()) {
+        let ours = our_properties.get(key);
+        let value = if ours == base_properties.get(key) {
+            their_properties.get(key)
+        } else {
+            ours
+        };
+        if let Some(value) = value {
+            merged_properties.insert(key.clone(), value.clone());
+        }
+    }
+    let mut merged: Show = serde_yaml::from_value(Value::Mapping(merged_properties))
+        .map_err(|err| format!("couldn't merge two versions of a show: {err}"))?;
+    let episode = ours.episode.max(theirs.episode);
+    if merged.episode != episode {
+        // the resume position was about an episode that got watched on the other side
+        merged.resume = None;
+    }
+    merged.episode = episode;
+    merged.downloaded = ours.downloaded.max(theirs.downloaded);
+    merged.updated = ours.updated.max(theirs.updated);
+    Ok(merged)
+}
+
+/// Lines added on either side are kept, lines removed on either side are removed, and every line
+/// only appears once. Our lines come first, then the ones only they added.
+fn merge_lines(base: &str, ours: &str, theirs: &str) -> String {
+    let base: HashSet<&str> = base.lines().collect();
+    let our_lines: HashSet<&str> = ours.lines().collect();
+    let their_lines: HashSet<&str> = theirs.lines().collect();
+    let mut seen = HashSet::new();
+    let mut merged = String::new();
+    for line in ours.lines().chain(theirs.lines()) {
+        let removed = base.contains(line) && !(our_lines.contains(line) && their_lines.contains(line));
+        if line.trim().is_empty() || removed || !seen.insert(line) {
+            continue;
+        }
+        merged.push_str(line);
+        merged.push('\n');
+    }
+    merged
+}
+
+/// Merges the lines like `merge_lines` does, and puts them back in the order they were logged in.
+fn merge_log(base: &str, ours: &str, theirs: &str) -> String {
+    let merged = merge_lines(base, ours, theirs);
+    let mut lines: Vec<&str> = merged.lines().collect();
+    lines.sort_by_key(|line| LogEntry::parse(line).map(|entry| entry.timestamp));
+    lines.iter().map(|line| format!("{line}\n")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::merge_current;
+    use super::merge_lines;
+    use super::merge_log;
+
+    #[test]
+    fn current_takes_furthest_progress() {
+        let base = "Frieren:\n  episode: 3\n  downloaded: 3\n  link: null\n  dlink: null\nMushishi:\n  episode: 1\n  downloaded: 1\n  link: null\n  dlink: null\n";
+        let ours = "Frieren:\n  episode: 5\n  downloaded: 4\n  link: null\n  dlink: null\nMushishi:\n  episode: 1\n  downloaded: 1\n  link: null\n  dlink: null\n";
+        let theirs = "Bleach:\n  episode: 0\n  downloaded: 0\n  link: null\n  dlink: null\nFrieren:\n  episode: 4\n  downloaded: 6\n  link: a\n  dlink: null\n";
+        let merged = merge_current(base, ours, theirs).unwrap();
+        assert_eq!(
+            merged,
+            "Bleach:\n  episode: 0\n  downloaded: 0\n  link: null\n  dlink: null\nFrieren:\n  episode: 5\n  downloaded: 6\n  link: a\n  dlink: null\n"
+        );
+    }
+
+    #[test]
+    fn lines_union_and_honor_removals() {
+        let base = "Frieren\nMushishi\n";
+        let ours = "Frieren\nMushishi\nBleach\n";
+        let theirs = "Mushishi\nMonster\nBleach\n";
+        assert_eq!(merge_lines(base, ours, theirs), "Mushishi\nBleach\nMonster\n");
+    }
+
+    #[test]
+    fn log_lines_sort_by_time() {
+        let base = "2024-03-01T10:00:00Z - ep0 -> ep1 - Frieren\n";
+        let ours =
+            "2024-03-01T10:00:00Z - ep0 -> ep1 - Frieren\n2024-03-03T10:00:00Z - ep1 -> ep2 - Frieren\n";
+        let theirs = "2024-03-01T10:00:00Z - ep0 -> ep1 - Frieren\n2024-03-02T10:00:00Z - ep3 -> ep4 - Mushishi\n2024-03-03T10:00:00Z - ep1 -> ep2 - Frieren\n";
+        assert_eq!(
+            merge_log(base, ours, theirs),
+            "2024-03-01T10:00:00Z - ep0 -> ep1 - Frieren\n2024-03-02T10:00:00Z - ep3 -> ep4 - Mushishi\n2024-03-03T10:00:00Z - ep1 -> ep2 - Frieren\n"
+        );
+    }
+}
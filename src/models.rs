@@ -1,5 +1,14 @@
 use std::fmt::Display;
+use std::fs;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
 
+pub mod entry;
+pub mod episode_files;
+pub mod history;
+pub mod log;
+pub mod schedule;
 pub mod show;
 pub mod watched;
 pub mod wl;
@@ -18,70 +27,137 @@ impl Display for ValidatedTitle {
     }
 }
 
+/// How a pattern matched a title.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatchKind {
+    ExactCaseSensitive,
+    ExactCaseInsensitive,
+    SubstringCaseInsensitive,
+    /// The case-insensitive substring matched several titles, but only one case-sensitively.
+    SubstringCaseSensitive,
+}
+
+/// Why a pattern didn't match a single title.
+#[derive(Debug, PartialEq)]
+pub enum PatternError {
+    NoMatch,
+    /// Too many titles contain the pattern case-insensitively, and then either none or too many
+    /// contain it case-sensitively.
+    Ambiguous {
+        insensitive: Vec<String>,
+        sensitive: Vec<String>,
+    },
+}
+
+impl PatternError {
+    /// The titles the pattern could have meant.
+    pub fn candidates(&self) -> &[String] {
+        match self {
+            Self::NoMatch => &[],
+            Self::Ambiguous {
+                insensitive,
+                sensitive,
+            } if sensitive.is_empty() => insensitive,
+            Self::Ambiguous { sensitive, .. } => sensitive,
+        }
+    }
+}
+
+impl Display for PatternError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoMatch => write!(f, "unsuccessful case-insensitive substring match"),
+            Self::Ambiguous { insensitive, sensitive } if sensitive.is_empty() => write!(
+                f,
+                "case-insensitive substring match (too many): '{}'\nand then, unsuccessful case-sensitive substring match",
+                insensitive.join("', '")
+            ),
+            Self::Ambiguous { insensitive, sensitive } => write!(
+                f,
+                "case-insensitive substring match (too many): '{}'\nand then, case-sensitive substring match (too many): '{}'",
+                insensitive.join("', '"),
+                sensitive.join("', '")
+            ),
+        }
+    }
+}
+
+/// Every title in `titles` once, in the order they first appear in.
+fn unique_titles<'a>(titles: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::new();
+    for title in titles {
+        if !unique.iter().any(|seen| seen == title) {
+            unique.push(title.to_owned());
+        }
+    }
+    unique
+}
+
 impl ValidatedTitle {
+    /// Like `from_pattern`, for titles that can repeat, like the ones of log lines.
+    fn from_repeated<'a>(titles: impl IntoIterator<Item = &'a str>, pattern: &str) -> Result<Self, String> {
+        Self::from_pattern(unique_titles(titles), pattern)
+    }
+
     fn from_pattern(titles: Vec<String>, pattern: &str) -> Result<Self, String> {
+        let (title, kind) = Self::try_match(&titles, pattern).map_err(|err| err.to_string())?;
+        match kind {
+            MatchKind::ExactCaseSensitive => eprintln!("successful exact case-sensitive match: {}", title),
+            MatchKind::ExactCaseInsensitive => {
+                eprintln!("successful exact case-insensitive match: {}", title)
+            },
+            MatchKind::SubstringCaseInsensitive => {
+                eprintln!("successful case-insensitive substring match: '{}'", title)
+            },
+            MatchKind::SubstringCaseSensitive => {
+                eprintln!("successful case-sensitive substring match: '{}'", title)
+            },
+        }
+        Ok(title)
+    }
+
+    /// Matches `pattern` against `titles`: exactly, then exactly ignoring case, then as a
+    /// substring ignoring case, narrowed down to matching case if that's ambiguous.
+    fn try_match(titles: &[String], pattern: &str) -> Result<(Self, MatchKind), PatternError> {
         let lowercase_pattern = pattern.to_lowercase();
 
-        for key in titles.iter() {
-            if key == pattern {
-                eprintln!("successful exact case-sensitive match: {}", key);
-                return Ok(ValidatedTitle(key.to_owned()));
-            }
+        if let Some(key) = titles.iter().find(|key| *key == pattern) {
+            return Ok((ValidatedTitle(key.to_owned()), MatchKind::ExactCaseSensitive));
         }
 
-        for key in titles.iter() {
-            if key.to_lowercase() == lowercase_pattern {
-                eprintln!("successful exact case-insensitive match: {}", key);
-                return Ok(ValidatedTitle(key.to_owned()));
-            }
+        if let Some(key) = titles.iter().find(|key| key.to_lowercase() == lowercase_pattern) {
+            return Ok((ValidatedTitle(key.to_owned()), MatchKind::ExactCaseInsensitive));
         }
 
-        let mut candidates: Vec<_> = titles
+        let mut insensitive: Vec<&String> = titles
             .iter()
-            .filter(|&show| show.to_lowercase().contains(&lowercase_pattern))
+            .filter(|show| show.to_lowercase().contains(&lowercase_pattern))
             .collect();
+        insensitive.sort();
 
-        if candidates.is_empty() {
-            return Err("unsuccessful case-insensitive substring match".into());
-        }
-
-        let insensitive_candidates = candidates.clone();
-        let mut retained = false;
-        if candidates.len() > 1 {
-            candidates.retain(|&show| show.contains(pattern));
-            retained = true;
-        }
-
-        match candidates.len() {
-            0 => Err(format!(
-            "case-insensitive substring match (too many): '{}'\nand then, unsuccessful case-sensitive substring match",
-                insensitive_candidates
-                    .iter()
-                    .map(|candidate| *candidate as &str)
-                    .collect::<Vec<&str>>()
-                    .join("', '")
+        match insensitive.as_slice() {
+            [] => Err(PatternError::NoMatch),
+            [show] => Ok((
+                ValidatedTitle(show.to_string()),
+                MatchKind::SubstringCaseInsensitive,
             )),
-            1 => {
-                // if we got here without retaining, that means we matched precisely a single show
-                // case-insensitively.
-                // if we did retain, that means we could only get to precisely 1 show once we
-                // searched case-sensitively
-                eprintln!("successful case-{}sensitive substring match: '{}'", if retained { "" } else { "in" } , candidates[0]);
-                Ok(ValidatedTitle(candidates[0].to_owned()))
-            },
-            _ => Err(format!(
-                "case-insensitive substring match (too many): '{}'\nand then, case-sensitive substring match (too many): '{}'",
-                insensitive_candidates
-                    .iter()
-                    .map(|candidate| *candidate as &str)
-                    .collect::<Vec<&str>>()
-                    .join("', '"),
-                candidates
+            _ => {
+                let sensitive: Vec<&String> = insensitive
                     .iter()
-                    .map(|candidate| *candidate as &str)
-                    .collect::<Vec<&str>>()
-                    .join("', '")
-            )),
+                    .copied()
+                    .filter(|show| show.contains(pattern))
+                    .collect();
+                match sensitive.as_slice() {
+                    [show] => Ok((
+                        ValidatedTitle(show.to_string()),
+                        MatchKind::SubstringCaseSensitive,
+                    )),
+                    _ => Err(PatternError::Ambiguous {
+                        insensitive: insensitive.into_iter().cloned().collect(),
+                        sensitive: sensitive.into_iter().cloned().collect(),
+                    }),
+                }
+            },
         }
     }
 
@@ -89,3 +165,17 @@ impl ValidatedTitle {
         &self.0
     }
 }
+
+/// Writes `contents` to a file next to `file_path` first, and then moves it over it, so the
+/// file is never left half written.
+fn write_atomically(file_path: &Path, contents: &str) -> Result<(), String> {
+    let file_name = file_path.file_name().unwrap_or_default().to_string_lossy();
+    let temporary_path = file_path.with_file_name(format!(".{file_name}.new"));
+    let mut temporary =
+        File::create(&temporary_path).map_err(|_| format!("couldn't create the new {file_name}"))?;
+    temporary
+        .write_all(contents.as_bytes())
+        .and_then(|_| temporary.sync_all())
+        .map_err(|_| format!("couldn't write the new {file_name}"))?;
+    fs::rename(&temporary_path, file_path).map_err(|_| format!("couldn't replace {file_name}"))
+}
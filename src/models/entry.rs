@@ -0,0 +1,176 @@
+use std::fmt::Display;
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::ValidatedTitle;
+use crate::data::CURRENT_FILE;
+use crate::data::WATCHED_FILE;
+use crate::data::WATCH_LATER_FILE;
+use crate::format::Fields;
+
+/// Where a show stands with you, whichever file it lives in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Status {
+    /// In current.yml.
+    #[default]
+    Watching,
+    /// In current.yml, but hidden from `shows` until you `resume` it.
+    OnHold,
+    /// In watch-later.txt.
+    Planned,
+    /// Finished, in watched.txt.
+    Completed,
+    /// Dropped, in watched.txt.
+    Dropped,
+}
+
+impl Status {
+    pub fn is_watching(&self) -> bool {
+        *self == Self::Watching
+    }
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Watching => write!(f, "watching"),
+            Self::OnHold => write!(f, "on hold"),
+            Self::Planned => write!(f, "planned"),
+            Self::Completed => write!(f, "completed"),
+            Self::Dropped => write!(f, "dropped"),
+        }
+    }
+}
+
+/// A show from any of the data files.
+pub struct Entry {
+    pub title: String,
+    pub status: Status,
+    /// The episode you're on, or dropped the show at.
+    pub episode: Option<u32>,
+    /// When you finished or dropped the show.
+    pub date: Option<NaiveDate>,
+    pub rewatch: Option<u32>,
+}
+
+/// The fields `wl --format` and `watched --format` can use.
+pub const ENTRY_FIELDS: &[&str] = &["title", "status", "episode", "date", "rewatch"];
+
+impl Entry {
+    /// The data file the entry lives in.
+    pub fn file_name(&self) -> &'static str {
+        match self.status {
+            Status::Watching | Status::OnHold => CURRENT_FILE,
+            Status::Planned => WATCH_LATER_FILE,
+            Status::Completed | Status::Dropped => WATCHED_FILE,
+        }
+    }
+
+    /// The values of `ENTRY_FIELDS` for this entry.
+    pub fn fields(&self) -> Fields {
+        let mut fields = Fields::new();
+        fields.insert("title", self.title.to_owned());
+        fields.insert("status", self.status.to_string());
+        let optional = [
+            ("episode", self.episode.map(|episode| episode.to_string())),
+            ("date", self.date.map(|date| date.format("%Y-%m-%d").to_string())),
+            ("rewatch", self.rewatch.map(|rewatch| rewatch.to_string())),
+        ];
+        for (name, value) in optional {
+            if let Some(value) = value {
+                fields.insert(name, value);
+            }
+        }
+        fields
+    }
+
+    /// Like `completed on 2023-09-03`, or `watching, on ep 4`.
+    pub fn describe(&self) -> String {
+        let mut description = self.status.to_string();
+        if let Some(episode) = self.episode {
+            match self.status {
+                Status::Dropped => description.push_str(&format!(" at ep {episode}")),
+                _ => description.push_str(&format!(", on ep {episode}")),
+            }
+        }
+        if let Some(date) = self.date {
+            description.push_str(&format!(" on {}", date.format("%Y-%m-%d")));
+        }
+        description
+    }
+}
+
+/// Matches `pattern` against the titles of every entry, the same way shows are matched, and gives
+/// back the last entry with the matched title.
+pub fn locate<'a>(entries: &'a [Entry], pattern: &str) -> Result<&'a Entry, String> {
+    let title = ValidatedTitle::from_repeated(entries.iter().map(|entry| entry.title.as_str()), pattern)?;
+    // from_repeated only gives back titles it was given
+    Ok(entries
+        .iter()
+        .rev()
+        .find(|entry| entry.title == title.as_str())
+        .unwrap())
+}
+
+/// Every entry whose title matches `pattern`: the exact matches (ignoring case) if there are any,
+/// and otherwise the ones that contain it (ignoring case as well).
+pub fn search<'a>(entries: &'a [Entry], pattern: &str) -> Vec<&'a Entry> {
+    let lowercase_pattern = pattern.to_lowercase();
+    let exact: Vec<&Entry> = entries
+        .iter()
+        .filter(|entry| entry.title.to_lowercase() == lowercase_pattern)
+        .collect();
+    if !exact.is_empty() {
+        return exact;
+    }
+    entries
+        .iter()
+        .filter(|entry| entry.title.to_lowercase().contains(&lowercase_pattern))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::search;
+    use super::Entry;
+    use super::Status;
+
+    #[test]
+    fn exact_matches_win() {
+        let entries = [
+            Entry {
+                title: "Frieren".into(),
+                status: Status::Completed,
+                episode: None,
+                date: None,
+                rewatch: None,
+            },
+            Entry {
+                title: "Frieren 2".into(),
+                status: Status::Planned,
+                episode: None,
+                date: None,
+                rewatch: None,
+            },
+            Entry {
+                title: "frieren".into(),
+                status: Status::Watching,
+                episode: None,
+                date: None,
+                rewatch: None,
+            },
+        ];
+        let titles = |pattern| {
+            search(&entries, pattern)
+                .into_iter()
+                .map(|entry| entry.title.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(titles("FRIEREN"), ["Frieren", "frieren"]);
+        assert_eq!(titles("rieren"), ["Frieren", "Frieren 2", "frieren"]);
+        assert!(titles("Mushishi").is_empty());
+    }
+}
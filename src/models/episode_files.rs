@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use regex::Regex;
+
+const EPISODE_PLACEHOLDER: &str = "{ep}";
+
+/// Turns a filename pattern into a regex that captures the episode number.
+/// A pattern containing `{ep}` is a template, where `{ep}` stands for the episode number and
+/// everything else is taken literally.
+/// Otherwise, the pattern is a regex that has to capture the episode number, either in a group
+/// named `ep`, or in its first group.
+pub fn compile_pattern(pattern: &str) -> Result<Regex, String> {
+    if pattern.contains(EPISODE_PLACEHOLDER) {
+        let escaped = pattern
+            .split(EPISODE_PLACEHOLDER)
+            .map(regex::escape)
+            .collect::<Vec<_>>()
+            .join(r"(?P<ep>\d+)");
+        return Regex::new(&format!("^{escaped}$")).map_err(|err| err.to_string());
+    }
+    let regex = Regex::new(pattern).map_err(|err| format!("invalid filename pattern: {err}"))?;
+    if regex.captures_len() < 2 {
+        return Err(format!(
+            "filename pattern '{pattern}' neither contains `{EPISODE_PLACEHOLDER}` nor captures the episode number in a group"
+        ));
+    }
+    Ok(regex)
+}
+
+/// Every file in `dir` (not recursively) whose name matches `pattern`, keyed by its episode number.
+pub fn find_episode_files(dir: &Path, pattern: &str) -> Result<BTreeMap<u32, PathBuf>, String> {
+    let regex = compile_pattern(pattern)?;
+    let entries =
+        fs::read_dir(dir).map_err(|_| format!("couldn't read the download directory {}", dir.display()))?;
+    let mut files = BTreeMap::new();
+    for entry in entries.flatten() {
+        let file_name = entry.file_name();
+        let Some(file_name) = file_name.to_str() else {
+            continue;
+        };
+        let Some(captures) = regex.captures(file_name) else {
+            continue;
+        };
+        let Some(episode) = captures.name("ep").or_else(|| captures.get(1)) else {
+            continue;
+        };
+        if let Ok(episode) = episode.as_str().parse() {
+            files.insert(episode, entry.path());
+        }
+    }
+    Ok(files)
+}
+
+/// The highest episode of the contiguous run of files that follows `downloaded`, if there are
+/// any, so that files of older episodes, or ones from after a gap, don't count.
+pub fn highest_contiguous(files: &BTreeMap<u32, PathBuf>, downloaded: u32) -> Option<u32> {
+    let mut highest = downloaded;
+    while files.contains_key(&(highest + 1)) {
+        highest += 1;
+    }
+    (highest > downloaded).then_some(highest)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use tempfile::TempDir;
+
+    use super::compile_pattern;
+    use super::find_episode_files;
+    use super::highest_contiguous;
+
+    fn episodes(numbers: &[u32]) -> BTreeMap<u32, PathBuf> {
+        numbers
+            .iter()
+            .map(|&episode| (episode, PathBuf::from(format!("{episode}.mkv"))))
+            .collect()
+    }
+
+    #[test]
+    fn contiguous_runs_stop_at_gaps() {
+        assert_eq!(highest_contiguous(&episodes(&[]), 0), None);
+        assert_eq!(highest_contiguous(&episodes(&[1, 2, 3]), 0), Some(3));
+        assert_eq!(highest_contiguous(&episodes(&[1, 2, 4, 5]), 0), Some(2));
+        assert_eq!(highest_contiguous(&episodes(&[3, 4, 7]), 2), Some(4));
+        assert_eq!(highest_contiguous(&episodes(&[3, 4, 7]), 0), None);
+    }
+
+    #[test]
+    fn downloaded_never_goes_down() {
+        // a stray old episode next to the new ones
+        assert_eq!(highest_contiguous(&episodes(&[1, 5, 6, 7]), 4), Some(7));
+        assert_eq!(highest_contiguous(&episodes(&[1, 5, 6, 7]), 7), None);
+        // the episodes before were deleted after watching them
+        assert_eq!(highest_contiguous(&episodes(&[6]), 5), Some(6));
+        assert_eq!(highest_contiguous(&episodes(&[6]), 9), None);
+    }
+
+    #[test]
+    fn templates_are_literal() {
+        let regex = compile_pattern("One Piece - {ep} [1080p].mkv").unwrap();
+        assert_eq!(
+            &regex.captures("One Piece - 1071 [1080p].mkv").unwrap()["ep"],
+            "1071"
+        );
+        assert!(!regex.is_match("One Piece - 1071 [720p].mkv"));
+        assert!(!regex.is_match("xOne Piece - 1071 [1080p].mkv"));
+        assert!(!regex.is_match("One Piece - 1071a [1080p].mkv"));
+    }
+
+    #[test]
+    fn regexes_need_a_group() {
+        assert!(compile_pattern(r"E(\d+)\.mkv").is_ok());
+        assert!(compile_pattern(r"E(?P<ep>\d+)\.mkv").is_ok());
+        assert!(compile_pattern(r"E\d+\.mkv").is_err());
+        assert!(compile_pattern(r"E(\d+").is_err());
+    }
+
+    #[test]
+    fn files_are_found_by_episode() {
+        let dir = TempDir::new().unwrap();
+        for file_name in [
+            "Frieren 01.mkv",
+            "Frieren 02.mkv",
+            "Frieren 04.mkv",
+            "Frieren 03.srt",
+            "notes.txt",
+        ] {
+            fs::write(dir.path().join(file_name), "").unwrap();
+        }
+        let files = find_episode_files(dir.path(), "Frieren {ep}.mkv").unwrap();
+        assert_eq!(files.keys().copied().collect::<Vec<_>>(), [1, 2, 4]);
+        assert_eq!(files[&4], dir.path().join("Frieren 04.mkv"));
+        assert_eq!(highest_contiguous(&files, 0), Some(2));
+        assert_eq!(highest_contiguous(&files, 3), Some(4));
+        let files = find_episode_files(dir.path(), r"^Frieren (?P<ep>\d+)\.(mkv|srt)$").unwrap();
+        assert_eq!(files.keys().copied().collect::<Vec<_>>(), [1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,185 @@
+use std::path::Path;
+
+use chrono::DateTime;
+use chrono::Local;
+
+use super::unique_titles;
+use super::ValidatedTitle;
+use crate::git::git_log;
+use crate::git::is_git_init;
+
+/// Something that happened to a show, according to a commit message.
+pub struct HistoryEvent {
+    pub time: DateTime<Local>,
+    pub title: String,
+    pub what: String,
+}
+
+impl HistoryEvent {
+    /// Understands a single line of a commit message that `glaza --git` makes.
+    fn parse(time: DateTime<Local>, line: &str) -> Option<Self> {
+        let event = |title: &str, what: String| {
+            Some(Self {
+                time,
+                title: title.to_owned(),
+                what,
+            })
+        };
+        let (action, rest) = line.split_once(" -> ")?;
+        match action {
+            "start" => event(rest, "started".into()),
+            "finish" => event(rest, "finished".into()),
+            "remove" => event(rest, "removed".into()),
+            "hold" => event(rest, "put on hold".into()),
+            "resume" => event(rest, "resumed".into()),
+            "wl add" => event(rest, "added to watch later".into()),
+            "watched edit" => event(rest, "watched list entry changed".into()),
+            "watched rm" => event(rest, "removed from the watched list".into()),
+            "wl remove" => event(rest, "removed from watch later".into()),
+            "schedule" => event(rest, "schedule set".into()),
+            "clear schedule" => event(rest, "schedule cleared".into()),
+            "clear total" => event(rest, "total episodes cleared".into()),
+            "update dir" => event(rest, "download directory set".into()),
+            "update link" | "update dlink" => {
+                let (title, link) = rest.rsplit_once(" -> ")?;
+                let kind = action.trim_start_matches("update ");
+                event(title, format!("{kind} changed to {link}"))
+            },
+            _ => {
+                if let Some(episode) = action.strip_prefix("drop at ") {
+                    event(rest, format!("dropped at ep {episode}"))
+                } else if let Some(episode) = action.strip_prefix("watch ep") {
+                    event(rest, format!("watched ep {episode}"))
+                } else if let Some(total) = action.strip_prefix("total ") {
+                    event(rest, format!("total episodes set to {total}"))
+                } else if let Some(title) = action.strip_prefix("rename ") {
+                    event(title, format!("renamed to {rest}"))
+                } else if let Some(episode) = action.strip_prefix("download ep") {
+                    event(rest, format!("downloaded ep {episode}"))
+                } else if let Some(paused) = action.strip_prefix("pause ep") {
+                    let (episode, position) = paused.split_once(" at ")?;
+                    event(rest, format!("paused ep {episode} at {position}"))
+                } else {
+                    None
+                }
+            },
+        }
+    }
+}
+
+/// The history of every show, as told by the git log of the data directory.
+pub struct HistoryRepo {
+    events: Vec<HistoryEvent>,
+}
+
+impl HistoryRepo {
+    pub fn normalize_show_pattern(&self, pattern: &str) -> Result<ValidatedTitle, String> {
+        ValidatedTitle::from_repeated(self.events.iter().map(|event| event.title.as_str()), pattern)
+    }
+
+    /// Every title that ever appeared in the history, in the order they first appeared in.
+    fn titles(&self) -> Vec<String> {
+        unique_titles(self.events.iter().map(|event| event.title.as_str()))
+    }
+
+    pub fn print(&self, show_title: Option<&ValidatedTitle>) -> Result<(), &'static str> {
+        let titles = match show_title {
+            Some(show_title) => vec![show_title.to_string()],
+            None => self.titles(),
+        };
+        if titles.is_empty() {
+            return Err("the git log of the data directory has no history of any show");
+        }
+        for (index, title) in titles.iter().enumerate() {
+            if index > 0 {
+                println!();
+            }
+            println!("{title}");
+            for event in self.events.iter().filter(|event| &event.title == title) {
+                println!("  {} — {}", event.time.format("%Y-%m-%d %H:%M"), event.what);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<&Path> for HistoryRepo {
+    type Error = String;
+
+    fn try_from(data_dir: &Path) -> Result<Self, Self::Error> {
+        if !is_git_init(data_dir) {
+            return Err("the data directory is not a git repository, so it has no history. use the `--git` flag to start one".into());
+        }
+        let events = git_log(data_dir)?
+            .into_iter()
+            .flat_map(|(time, message)| {
+                message
+                    .lines()
+                    .filter_map(|line| HistoryEvent::parse(time, line))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        Ok(Self { events })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Local;
+
+    use super::HistoryEvent;
+
+    fn parse(line: &str) -> Option<(String, String)> {
+        HistoryEvent::parse(Local::now(), line).map(|event| (event.title, event.what))
+    }
+
+    #[test]
+    fn commit_messages_parse() {
+        let parsed = |title: &str, what: &str| Some((title.to_owned(), what.to_owned()));
+        assert_eq!(parse("start -> Frieren"), parsed("Frieren", "started"));
+        assert_eq!(parse("watch ep4 -> Frieren"), parsed("Frieren", "watched ep 4"));
+        assert_eq!(
+            parse("drop at 3 -> Mushishi"),
+            parsed("Mushishi", "dropped at ep 3")
+        );
+        assert_eq!(
+            parse("pause ep5 at 312s -> Frieren"),
+            parsed("Frieren", "paused ep 5 at 312s")
+        );
+        assert_eq!(
+            parse("update dlink -> Frieren -> https://website.com/frieren-"),
+            parsed("Frieren", "dlink changed to https://website.com/frieren-")
+        );
+        assert_eq!(
+            parse("total 28 -> Frieren"),
+            parsed("Frieren", "total episodes set to 28")
+        );
+        assert_eq!(
+            parse("clear total -> Frieren"),
+            parsed("Frieren", "total episodes cleared")
+        );
+        assert_eq!(
+            parse("rename Freiren -> Frieren"),
+            parsed("Freiren", "renamed to Frieren")
+        );
+        assert_eq!(
+            parse("watched rm -> Bleach"),
+            parsed("Bleach", "removed from the watched list")
+        );
+    }
+
+    #[test]
+    fn other_lines_are_skipped() {
+        for line in [
+            "",
+            "Merge branch 'main'",
+            "start Frieren",
+            "doctor fix",
+            "sneeze -> Frieren",
+            "pause ep5 -> Frieren",
+            "update link -> Frieren",
+        ] {
+            assert_eq!(parse(line), None, "{line}");
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use chrono::DateTime;
+use chrono::Local;
+use chrono::NaiveDate;
+use chrono::SecondsFormat;
+use chrono::Utc;
+
+use super::write_atomically;
+use super::ValidatedTitle;
+use crate::backup::snapshot;
+
+pub struct LogEntry {
+    pub timestamp: DateTime<Utc>,
+    pub from: u32,
+    pub to: u32,
+    pub title: String,
+}
+
+impl LogEntry {
+    /// Parses a line that looks like `2023-09-03T12:00:00Z - ep3 -> ep4 - Title`.
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut parts = line.splitn(3, " - ");
+        let timestamp = DateTime::parse_from_rfc3339(parts.next()?).ok()?;
+        let (from, to) = parts.next()?.split_once(" -> ")?;
+        let from = from.strip_prefix("ep")?.parse().ok()?;
+        let to = to.strip_prefix("ep")?.parse().ok()?;
+        let title = parts.next()?.to_owned();
+        Some(Self {
+            timestamp: timestamp.with_timezone(&Utc),
+            from,
+            to,
+            title,
+        })
+    }
+
+    fn render(&self) -> String {
+        format!(
+            "{} - ep{} -> ep{} - {}",
+            self.timestamp.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.from,
+            self.to,
+            self.title
+        )
+    }
+}
+
+pub struct LogRepo {
+    entries: Vec<LogEntry>,
+    file_path: PathBuf,
+}
+
+impl LogRepo {
+    pub fn normalize_show_pattern(&self, pattern: &str) -> Result<ValidatedTitle, String> {
+        ValidatedTitle::from_repeated(self.entries.iter().map(|entry| entry.title.as_str()), pattern)
+    }
+
+    pub fn record(&mut self, show_title: &ValidatedTitle, from: u32, to: u32) -> Result<(), &'static str> {
+        let entry = LogEntry {
+            timestamp: Utc::now(),
+            from,
+            to,
+            title: show_title.to_string(),
+        };
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(&self.file_path)
+            .map_err(|_| "couldn't open the episode log for appending")?;
+        writeln!(file, "{}", entry.render()).map_err(|_| "couldn't append to the episode log")?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Moves every entry of `title` over to `new_title`, and tells how many there were.
+    pub fn rename(&mut self, title: &str, new_title: &str) -> Result<usize, String> {
+        if self.has_title(new_title) {
+            return Err(format!("the episode log already has episodes of '{new_title}'"));
+        }
+        let contents = fs::read_to_string(&self.file_path).map_err(|_| "couldn't read the episode log")?;
+        let mut renamed = 0;
+        let mut lines = Vec::new();
+        for line in contents.lines() {
+            match LogEntry::parse(line) {
+                Some(mut entry) if entry.title == title => {
+                    entry.title = new_title.to_owned();
+                    lines.push(entry.render());
+                    renamed += 1;
+                },
+                _ => lines.push(line.to_owned()),
+            }
+        }
+        if renamed > 0 {
+            snapshot(&self.file_path)?;
+            write_atomically(&self.file_path, &(lines.join("\n") + "\n"))?;
+            for entry in self.entries.iter_mut().filter(|entry| entry.title == title) {
+                entry.title = new_title.to_owned();
+            }
+        }
+        Ok(renamed)
+    }
+
+    pub fn has_title(&self, title: &str) -> bool {
+        self.entries.iter().any(|entry| entry.title == title)
+    }
+
+    /// Every entry, oldest first.
+    pub fn entries(&self) -> &[LogEntry] {
+        &self.entries
+    }
+
+    pub fn last_watched(&self, show_title: &str) -> Option<DateTime<Utc>> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.title == show_title)
+            .map(|entry| entry.timestamp)
+    }
+
+    /// The entries of `show_title` (or every show), from the day `since` on, oldest first.
+    fn matching(&self, show_title: Option<&ValidatedTitle>, since: Option<NaiveDate>) -> Vec<&LogEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| show_title.is_none_or(|show_title| entry.title == show_title.as_str()))
+            .filter(|entry| {
+                since.is_none_or(|since| entry.timestamp.with_timezone(&Local).date_naive() >= since)
+            })
+            .collect()
+    }
+
+    pub fn print(
+        &self,
+        show_title: Option<&ValidatedTitle>,
+        since: Option<NaiveDate>,
+    ) -> Result<(), &'static str> {
+        let entries = self.matching(show_title, since);
+        if entries.is_empty() {
+            return Err("no matching entries in the episode log");
+        }
+        for entry in entries {
+            println!(
+                "{} - ep{} -> ep{} - {}",
+                entry.timestamp.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+                entry.from,
+                entry.to,
+                entry.title
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Humanizes how long ago `timestamp` was, in whole days.
+pub fn ago(timestamp: DateTime<Utc>) -> String {
+    let days = (Local::now().date_naive() - timestamp.with_timezone(&Local).date_naive()).num_days();
+    match days {
+        ..=0 => "today".into(),
+        1 => "yesterday".into(),
+        days => format!("{days} days ago"),
+    }
+}
+
+impl TryFrom<&Path> for LogRepo {
+    type Error = &'static str;
+
+    fn try_from(file_path: &Path) -> Result<Self, Self::Error> {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .open(file_path)
+            .map_err(|_| "couldn't create and/or open the episode log")?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)
+            .map_err(|_| "couldn't read the episode log, despite it existing")?;
+        let entries = contents.lines().filter_map(LogEntry::parse).collect();
+        Ok(Self {
+            entries,
+            file_path: file_path.to_path_buf(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::DateTime;
+    use chrono::NaiveDate;
+    use chrono::Utc;
+    use tempfile::TempDir;
+
+    use super::LogEntry;
+    use super::LogRepo;
+    use crate::models::ValidatedTitle;
+
+    fn log(contents: &str) -> (TempDir, LogRepo) {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("episodes.txt");
+        fs::write(&path, contents).unwrap();
+        let log_model = LogRepo::try_from(path.as_path()).unwrap();
+        (dir, log_model)
+    }
+
+    #[test]
+    fn lines_parse() {
+        let entry = LogEntry::parse("2024-03-01T19:00:00+09:00 - ep3 -> ep4 - Mushishi - Zoku Shou").unwrap();
+        assert_eq!(
+            entry.timestamp,
+            DateTime::parse_from_rfc3339("2024-03-01T10:00:00Z").unwrap()
+        );
+        assert_eq!((entry.from, entry.to), (3, 4));
+        assert_eq!(entry.title, "Mushishi - Zoku Shou");
+        for malformed in [
+            "",
+            "Frieren",
+            "yesterday - ep0 -> ep1 - Frieren",
+            "2024-03-01T10:00:00Z - 0 -> 1 - Frieren",
+            "2024-03-01T10:00:00Z - ep0 - Frieren",
+            "2024-03-01T10:00:00Z - epx -> ep1 - Frieren",
+            "2024-03-01T10:00:00Z - ep0 -> ep1",
+        ] {
+            assert!(LogEntry::parse(malformed).is_none(), "{malformed}");
+        }
+    }
+
+    #[test]
+    fn last_watched_skips_malformed_lines() {
+        let (_dir, log_model) = log(
+            "2024-03-01T12:00:00Z - ep0 -> ep1 - Frieren\nnot a log line\n2024-03-02T12:00:00Z - ep3 -> ep4 - Mushishi\n2024-03-03T12:00:00Z - ep1 -> ep2 - Frieren\n",
+        );
+        assert_eq!(log_model.entries().len(), 3);
+        assert_eq!(
+            log_model.last_watched("Frieren"),
+            Some(
+                DateTime::parse_from_rfc3339("2024-03-03T12:00:00Z")
+                    .unwrap()
+                    .with_timezone(&Utc)
+            )
+        );
+        assert_eq!(log_model.last_watched("Monster"), None);
+    }
+
+    #[test]
+    fn entries_filter_by_show_and_day() {
+        let (_dir, log_model) = log(
+            "2024-03-01T12:00:00Z - ep0 -> ep1 - Frieren\n2024-03-02T12:00:00Z - ep3 -> ep4 - Mushishi\n2024-03-03T12:00:00Z - ep1 -> ep2 - Frieren\n",
+        );
+        let frieren = ValidatedTitle("Frieren".into());
+        let since = NaiveDate::from_ymd_opt(2024, 3, 2);
+        let to = |entries: Vec<&LogEntry>| entries.iter().map(|entry| entry.to).collect::<Vec<_>>();
+        assert_eq!(to(log_model.matching(None, None)), [1, 4, 2]);
+        assert_eq!(to(log_model.matching(Some(&frieren), None)), [1, 2]);
+        assert_eq!(to(log_model.matching(None, since)), [4, 2]);
+        assert_eq!(to(log_model.matching(Some(&frieren), since)), [2]);
+        assert!(log_model
+            .matching(None, NaiveDate::from_ymd_opt(2024, 3, 4))
+            .is_empty());
+    }
+
+    #[test]
+    fn renaming_moves_the_entries_over() {
+        let (dir, mut log_model) = log(
+            "2024-03-01T10:00:00Z - ep0 -> ep1 - Freiren\n2024-03-02T10:00:00Z - ep3 -> ep4 - Mushishi\n2024-03-03T10:00:00Z - ep1 -> ep2 - Freiren\n",
+        );
+        assert!(log_model.rename("Freiren", "Mushishi").is_err());
+        assert_eq!(log_model.rename("Freiren", "Frieren").unwrap(), 2);
+        assert!(log_model.last_watched("Frieren").is_some());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("episodes.txt")).unwrap(),
+            "2024-03-01T10:00:00Z - ep0 -> ep1 - Frieren\n2024-03-02T10:00:00Z - ep3 -> ep4 - Mushishi\n2024-03-03T10:00:00Z - ep1 -> ep2 - Frieren\n"
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use chrono::DateTime;
+use chrono::Datelike;
+use chrono::Duration;
+use chrono::FixedOffset;
+use chrono::Local;
+use chrono::NaiveDate;
+use chrono::NaiveDateTime;
+use chrono::NaiveTime;
+use chrono::TimeZone;
+use chrono::Weekday;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// When the episodes of a show come out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Schedule {
+    /// A new episode every week, on the same weekday and time.
+    Weekly {
+        weekday: Weekday,
+        time: NaiveTime,
+        /// The timezone `time` is in. Your local timezone if unset.
+        #[serde(default, skip_serializing_if = "Option::is_none", with = "utc_offset")]
+        utc_offset: Option<FixedOffset>,
+        /// The first matching weekday on or after this date is when `first_episode` airs.
+        since: NaiveDate,
+        #[serde(default = "default_first_episode")]
+        first_episode: u32,
+    },
+    /// Explicit air dates, the first one being the air date of episode 1.
+    Dates(Vec<DateTime<FixedOffset>>),
+}
+
+fn default_first_episode() -> u32 {
+    1
+}
+
+impl Schedule {
+    /// Every air date of the show, in order, along with the episode that airs on it.
+    /// Weekly schedules never end, so this iterator doesn't either.
+    pub fn air_dates(&self) -> Box<dyn Iterator<Item = (u32, DateTime<FixedOffset>)> + '_> {
+        match self {
+            Self::Weekly {
+                weekday,
+                time,
+                utc_offset,
+                since,
+                first_episode,
+            } => {
+                let days_until_weekday =
+                    (7 + weekday.num_days_from_monday() - since.weekday().num_days_from_monday()) % 7;
+                let first_date = *since + Duration::days(days_until_weekday.into());
+                match utc_offset {
+                    Some(utc_offset) => Box::new(weekly(*utc_offset, first_date, *time, *first_episode)),
+                    None => Box::new(weekly(Local, first_date, *time, *first_episode)),
+                }
+            },
+            Self::Dates(dates) => Box::new(
+                dates
+                    .iter()
+                    .enumerate()
+                    .map(|(index, date)| (index as u32 + 1, *date)),
+            ),
+        }
+    }
+
+    /// The latest episode that has aired by now, which is never past the `total` of the show.
+    pub fn aired(&self, total: Option<u32>) -> u32 {
+        let now = Local::now();
+        self.air_dates()
+            .take_while(|(episode, date)| *date <= now && total.is_none_or(|total| *episode <= total))
+            .last()
+            .map(|(episode, _)| episode)
+            .unwrap_or_default()
+    }
+}
+
+/// The air dates of a weekly show whose first episode airs at `time` on `first_date`, in
+/// `timezone`.
+/// Every week is at `time` in `timezone` as it is on that day, so episodes keep airing at the
+/// same wall clock time across DST changes.
+fn weekly<Tz: TimeZone>(
+    timezone: Tz,
+    first_date: NaiveDate,
+    time: NaiveTime,
+    first_episode: u32,
+) -> impl Iterator<Item = (u32, DateTime<FixedOffset>)> {
+    (0..).map(move |week: u32| {
+        let air = (first_date + Duration::weeks(week.into())).and_time(time);
+        // the earliest of an ambiguous local time is picked, and a nonexistent one (in a DST
+        // gap) is moved past the gap
+        let date = timezone
+            .from_local_datetime(&air)
+            .earliest()
+            .or_else(|| {
+                timezone
+                    .from_local_datetime(&(air + Duration::hours(1)))
+                    .earliest()
+            })
+            .map(|date| date.fixed_offset())
+            .unwrap_or_else(|| air.and_utc().fixed_offset());
+        (first_episode + week, date)
+    })
+}
+
+/// (De)serializes an optional UTC offset as a string like `+09:00`.
+pub mod utc_offset {
+    use chrono::FixedOffset;
+    use serde::de::Error;
+    use serde::Deserialize;
+    use serde::Deserializer;
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(offset: &Option<FixedOffset>, serializer: S) -> Result<S::Ok, S::Error> {
+        match offset {
+            Some(offset) => serializer.serialize_str(&offset.to_string()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<FixedOffset>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(offset) => offset.parse().map(Some).map_err(D::Error::custom),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Parses an air date given as either RFC 3339, `YYYY-MM-DD HH:MM` or `YYYY-MM-DD`.
+/// The latter two are taken to be in your local timezone, with a bare date meaning midnight.
+pub fn parse_air_date(input: &str) -> Result<DateTime<FixedOffset>, String> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(input) {
+        return Ok(date);
+    }
+    let naive = NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M")
+        .or_else(|_| NaiveDateTime::parse_from_str(input, "%Y-%m-%dT%H:%M"))
+        .or_else(|_| NaiveDate::parse_from_str(input, "%Y-%m-%d").map(|date| date.and_time(NaiveTime::MIN)))
+        .map_err(|_| {
+            format!("'{input}' is not a date in one of the formats: RFC 3339, YYYY-MM-DD HH:MM, YYYY-MM-DD")
+        })?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|date| date.fixed_offset())
+        .ok_or_else(|| format!("'{input}' doesn't exist in your local timezone"))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use chrono::FixedOffset;
+    use chrono::NaiveDate;
+    use chrono::NaiveTime;
+    use chrono_tz::Europe::Berlin;
+
+    use super::weekly;
+    use super::Schedule;
+
+    fn at(date: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(date).unwrap()
+    }
+
+    #[test]
+    fn weekly_starts_on_the_weekday() {
+        let schedule: Schedule = serde_yaml::from_str(
+            "!weekly\nweekday: Thu\ntime: 18:00:00\nutc_offset: +09:00\nsince: 2024-03-20\nfirst_episode: 5\n",
+        )
+        .unwrap();
+        let dates: Vec<_> = schedule.air_dates().take(3).collect();
+        assert_eq!(dates, [
+            (5, at("2024-03-21T18:00:00+09:00")),
+            (6, at("2024-03-28T18:00:00+09:00")),
+            (7, at("2024-04-04T18:00:00+09:00")),
+        ]);
+    }
+
+    #[test]
+    fn aired_stops_at_the_total() {
+        let schedule: Schedule =
+            serde_yaml::from_str("!weekly\nweekday: Thu\ntime: 18:00:00\nsince: 2024-03-20\n").unwrap();
+        assert!(schedule.aired(None) > 12);
+        assert_eq!(schedule.aired(Some(12)), 12);
+        let schedule = Schedule::Dates(vec![
+            at("2024-03-21T18:00:00+09:00"),
+            at("2024-03-28T18:00:00+09:00"),
+            at("2024-04-04T18:00:00+09:00"),
+        ]);
+        assert_eq!(schedule.aired(None), 3);
+        assert_eq!(schedule.aired(Some(2)), 2);
+    }
+
+    #[test]
+    fn weekly_keeps_the_time_across_dst() {
+        let first_date = NaiveDate::from_ymd_opt(2024, 3, 21).unwrap();
+        let time = NaiveTime::from_hms_opt(18, 0, 0).unwrap();
+        let dates: Vec<_> = weekly(Berlin, first_date, time, 1)
+            .take(3)
+            .map(|(_, date)| date)
+            .collect();
+        assert_eq!(dates, [
+            at("2024-03-21T18:00:00+01:00"),
+            at("2024-03-28T18:00:00+01:00"),
+            at("2024-04-04T18:00:00+02:00"),
+        ]);
+        // 02:30 doesn't exist on the day the clocks go forward
+        let time = NaiveTime::from_hms_opt(2, 30, 0).unwrap();
+        let first_date = NaiveDate::from_ymd_opt(2024, 3, 24).unwrap();
+        let dates: Vec<_> = weekly(Berlin, first_date, time, 1)
+            .take(2)
+            .map(|(_, date)| date)
+            .collect();
+        assert_eq!(dates, [
+            at("2024-03-24T02:30:00+01:00"),
+            at("2024-03-31T03:30:00+02:00"),
+        ]);
+    }
+}
@@ -5,21 +5,65 @@ use std::io::BufReader;
 use std::path::Path;
 use std::path::PathBuf;
 
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Local;
+use chrono::Utc;
+use clap::ValueEnum;
 use indexmap::IndexMap;
 use serde::Deserialize;
 use serde::Serialize;
 
+use super::entry::Entry;
+use super::entry::Status;
+use super::episode_files::find_episode_files;
+use super::episode_files::highest_contiguous;
+use super::log::ago;
+use super::log::LogRepo;
+use super::schedule::Schedule;
+use super::PatternError;
 use super::ValidatedTitle;
+use crate::backup::snapshot;
+use crate::format::Fields;
+use crate::format::Template;
 use crate::sh::open_in_browser;
+use crate::table::terminal_width;
+use crate::table::Color;
+use crate::table::Table;
 
-type Shows = HashMap<String, Show>;
+pub type Shows = HashMap<String, Show>;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Show {
     pub episode: u32,
     pub downloaded: u32,
     pub link: Option<String>,
     pub dlink: Option<String>,
+    /// How many episodes the show has, if you know.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<Schedule>,
+    /// The directory you download the episodes of the show into.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download_dir: Option<PathBuf>,
+    /// The filename pattern of the episodes in `download_dir`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// Where (in seconds) you stopped watching the next episode in mpv.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resume: Option<f64>,
+    /// Which time you're watching the show, if you finished it before: 2 for the first rewatch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rewatch: Option<u32>,
+    #[serde(default, skip_serializing_if = "Status::is_watching")]
+    pub status: Status,
+    /// When you started the show.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub added: Option<DateTime<Utc>>,
+    /// When you last changed anything about the show.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated: Option<DateTime<Utc>>,
 }
 
 impl Show {
@@ -29,6 +73,15 @@ impl Show {
             downloaded: 0,
             link: link.map(|value| value.to_owned()),
             dlink: dlink.map(|value| value.to_owned()),
+            total: None,
+            schedule: None,
+            download_dir: None,
+            pattern: None,
+            resume: None,
+            rewatch: None,
+            status: Status::Watching,
+            added: None,
+            updated: None,
         }
     }
 
@@ -46,6 +99,156 @@ impl Show {
         }
         Default::default()
     }
+
+    /// How many downloaded episodes you haven't watched yet.
+    fn backlog(&self) -> u32 {
+        self.downloaded.saturating_sub(self.episode)
+    }
+
+    fn cell(&self, column: Column, title: &str, log: &LogRepo) -> String {
+        match column {
+            Column::Title => match self.rewatch {
+                Some(rewatch) => format!("{title} (rewatch #{rewatch})"),
+                None => title.to_owned(),
+            },
+            Column::Ep => format!("ep{}", self.episode),
+            Column::Dn => format!("dn{}", self.downloaded),
+            Column::Total => self.total.map(|total| format!("of {total}")).unwrap_or_default(),
+            Column::Backlog => format!("{} to watch", self.backlog()),
+            Column::Link => self.link.clone().unwrap_or_default(),
+            Column::Dlink => self.dlink.clone().unwrap_or_default(),
+            Column::Status => self.status.to_string(),
+            Column::Last => log
+                .last_watched(title)
+                .map(|timestamp| format!("last watched {}", ago(timestamp)))
+                .unwrap_or_default(),
+            Column::Added => self
+                .added
+                .map(|added| format!("added {}", format_time(added)))
+                .unwrap_or_default(),
+            Column::Updated => self
+                .updated
+                .map(|updated| format!("updated {}", format_time(updated)))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Close to the finale beats having a backlog, which beats being stale.
+    fn color(&self) -> Option<Color> {
+        if self
+            .total
+            .is_some_and(|total| total.saturating_sub(self.episode) <= FINALE_EPISODES)
+        {
+            Some(Color::Magenta)
+        } else if self.backlog() > 0 {
+            Some(Color::Green)
+        } else if self
+            .updated
+            .is_some_and(|updated| Utc::now() - updated > Duration::days(STALE_DAYS))
+        {
+            Some(Color::Dim)
+        } else {
+            None
+        }
+    }
+
+    /// The values of `SHOW_FIELDS` for this show.
+    fn fields(&self, title: &str, log: &LogRepo) -> Fields {
+        let mut fields = Fields::new();
+        fields.insert("title", title.to_owned());
+        fields.insert("episode", self.episode.to_string());
+        fields.insert("downloaded", self.downloaded.to_string());
+        fields.insert("backlog", self.backlog().to_string());
+        fields.insert("status", self.status.to_string());
+        let optional = [
+            ("total", self.total.map(|total| total.to_string())),
+            ("link", self.link.clone()),
+            ("dlink", self.dlink.clone()),
+            ("rewatch", self.rewatch.map(|rewatch| rewatch.to_string())),
+            ("last_watched", log.last_watched(title).map(ago)),
+            ("added", self.added.map(format_time)),
+            ("updated", self.updated.map(format_time)),
+        ];
+        for (name, value) in optional {
+            if let Some(value) = value {
+                fields.insert(name, value);
+            }
+        }
+        fields
+    }
+}
+
+/// The fields `shows --format` can use.
+pub const SHOW_FIELDS: &[&str] = &[
+    "title",
+    "episode",
+    "downloaded",
+    "total",
+    "backlog",
+    "link",
+    "dlink",
+    "status",
+    "rewatch",
+    "last_watched",
+    "added",
+    "updated",
+];
+
+/// The columns `shows` can print.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Column {
+    Title,
+    Ep,
+    Dn,
+    Total,
+    Backlog,
+    Link,
+    Dlink,
+    Status,
+    /// When you last watched an episode.
+    Last,
+    Added,
+    Updated,
+}
+
+impl Column {
+    pub const DEFAULT: [Self; 4] = [Self::Title, Self::Ep, Self::Dn, Self::Last];
+}
+
+/// Shows you haven't changed in this many days are dimmed.
+const STALE_DAYS: i64 = 14;
+/// Shows with this many episodes left or less are close to their finale.
+const FINALE_EPISODES: u32 = 2;
+
+/// What `shows` sorts by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum SortKey {
+    /// Alphabetically.
+    #[default]
+    Title,
+    /// Most recently changed first.
+    Recent,
+    /// Furthest watched first.
+    Progress,
+    /// Most downloaded but unwatched episodes first.
+    Backlog,
+    /// Most recently started first.
+    Added,
+}
+
+/// Which shows `shows` lists, and how.
+pub struct ListOptions {
+    pub links: bool,
+    pub on_hold: bool,
+    pub sort: SortKey,
+    pub reverse: bool,
+    pub has_backlog: bool,
+    pub no_link: bool,
+    /// Only the shows you haven't changed in this many days.
+    pub stale: Option<u32>,
+    pub format: Option<Template>,
+    pub columns: Vec<Column>,
+    pub color: bool,
 }
 
 pub struct CurrentRepo {
@@ -58,11 +261,31 @@ impl CurrentRepo {
         ValidatedTitle::from_pattern(self.current.keys().cloned().collect::<Vec<String>>(), pattern)
     }
 
+    /// Like `normalize_show_pattern`, without printing how the pattern matched.
+    pub fn match_show_pattern(&self, pattern: &str) -> Result<ValidatedTitle, PatternError> {
+        let titles: Vec<String> = self.current.keys().cloned().collect();
+        ValidatedTitle::try_match(&titles, pattern).map(|(title, _)| title)
+    }
+
+    /// Every change to a show goes through here, so this is where it gets marked as updated.
     fn get_mut_show(&mut self, show_title: &ValidatedTitle) -> &mut Show {
-        self.current.get_mut(&show_title.0).unwrap()
+        let show = self.current.get_mut(&show_title.0).unwrap();
+        show.updated = Some(Utc::now());
+        show
+    }
+
+    /// Every show you're watching or put on hold, sorted by title.
+    pub fn shows(&self) -> Vec<(ValidatedTitle, &Show)> {
+        let mut shows: Vec<_> = self
+            .current
+            .iter()
+            .map(|(show_title, show)| (ValidatedTitle(show_title.to_owned()), show))
+            .collect();
+        shows.sort_by(|a, b| a.0 .0.cmp(&b.0 .0));
+        shows
     }
 
-    fn get_show(&self, show_title: &ValidatedTitle) -> &Show {
+    pub fn get_show(&self, show_title: &ValidatedTitle) -> &Show {
         self.current.get(&show_title.0).unwrap()
     }
 
@@ -71,72 +294,175 @@ impl CurrentRepo {
         show_title: &str,
         link: Option<&String>,
         dlink: Option<&String>,
-    ) -> Result<(), &'static str> {
-        self.current.insert(show_title.to_owned(), Show::new(link, dlink));
+        rewatch: Option<u32>,
+        added: DateTime<Utc>,
+    ) -> Result<(), String> {
+        let show = Show {
+            rewatch,
+            added: Some(added),
+            updated: Some(Utc::now()),
+            ..Show::new(link, dlink)
+        };
+        self.current.insert(show_title.to_owned(), show);
         self.save()
     }
 
-    pub fn list(&self, should_links: bool) -> Result<(), &'static str> {
-        let longest_title = match self.current.keys().map(|show_name| show_name.len()).max() {
-            Some(length) => length,
-            None => return Err("you have no shows you're currently watching"),
+    /// The shows `list` lists, in the order it lists them.
+    fn listed(&self, options: &ListOptions) -> Vec<(&String, &Show)> {
+        let status = if options.on_hold {
+            Status::OnHold
+        } else {
+            Status::Watching
         };
-        // this unwrap is safe because we just confirmed the iterator wouldn't be empty
-        let biggest_episode = self
+        let stale_since = options.stale.map(|days| Utc::now() - Duration::days(days.into()));
+        let mut listed: Vec<(&String, &Show)> = self
             .current
-            .values()
-            .map(|show| show.episode.to_string().len())
-            .max()
-            .unwrap();
-        for (show_title, show_obj) in self.current.iter() {
-            if !should_links {
-                let title_diff = " ".repeat(longest_title - show_title.len());
-                let episode_diff = " ".repeat(biggest_episode - show_obj.episode.to_string().len());
-                println!(
-                    "{show_title}{title_diff} — ep{}{episode_diff} — dn{}",
-                    show_obj.episode, show_obj.downloaded
-                );
-            } else {
-                const LONG_SEPARATOR: &str = "  ";
-                println!(
-                    "{show_title} — ep{} — dn{}",
-                    show_obj.episode, show_obj.downloaded
-                );
-                if let Some(link) = show_obj.link.as_ref() {
-                    println!("{0}link: {1}", LONG_SEPARATOR, link);
-                } else {
-                    println!("{0}link: empty", LONG_SEPARATOR);
-                }
-                if let Some(dlink) = show_obj.dlink.as_ref() {
-                    println!("{0}dlink: {1}", LONG_SEPARATOR, dlink);
+            .iter()
+            .filter(|(_, show)| show.status == status)
+            .filter(|(_, show)| !options.has_backlog || show.backlog() > 0)
+            .filter(|(_, show)| !options.no_link || show.link.is_none())
+            .filter(|(_, show)| {
+                stale_since.is_none_or(|stale_since| show.updated.is_none_or(|updated| updated < stale_since))
+            })
+            .collect();
+        listed.sort_by(|(a_title, a), (b_title, b)| {
+            match options.sort {
+                SortKey::Title => a_title.cmp(b_title),
+                SortKey::Recent => b.updated.cmp(&a.updated),
+                SortKey::Progress => b.episode.cmp(&a.episode),
+                SortKey::Backlog => b.backlog().cmp(&a.backlog()),
+                SortKey::Added => b.added.cmp(&a.added),
+            }
+            .then_with(|| a_title.cmp(b_title))
+        });
+        if options.reverse {
+            listed.reverse();
+        }
+        listed
+    }
+
+    /// Lists the shows you're watching, or with `on_hold`, the ones you put on hold.
+    pub fn list(&self, options: &ListOptions, log: &LogRepo) -> Result<(), &'static str> {
+        let listed = self.listed(options);
+        if listed.is_empty() {
+            return Err(
+                if options.has_backlog || options.no_link || options.stale.is_some() {
+                    "none of your shows match the filters"
+                } else if options.on_hold {
+                    "you have no shows on hold"
                 } else {
-                    println!("{0}dlink: empty", LONG_SEPARATOR);
-                }
-            };
+                    "you have no shows you're currently watching"
+                },
+            );
+        }
+        if let Some(template) = options.format.as_ref() {
+            let rows: Vec<Fields> = listed
+                .iter()
+                .map(|(show_title, show)| show.fields(show_title, log))
+                .collect();
+            template.render(&rows).iter().for_each(|line| println!("{line}"));
+            return Ok(());
+        }
+        if !options.links {
+            let mut table = Table::default();
+            for (show_title, show) in listed {
+                let cells = options
+                    .columns
+                    .iter()
+                    .map(|column| show.cell(*column, show_title, log))
+                    .collect();
+                table.push(cells, show.color());
+            }
+            table
+                .render(options.color, terminal_width())
+                .iter()
+                .for_each(|line| println!("{line}"));
+            return Ok(());
+        }
+        for (show_title, show_obj) in listed {
+            let mut details = log
+                .last_watched(show_title)
+                .map(|timestamp| format!(" — last watched {}", ago(timestamp)))
+                .unwrap_or_default();
+            if let Some(rewatch) = show_obj.rewatch {
+                details.push_str(&format!(" (rewatch #{rewatch})"));
+            }
+            const LONG_SEPARATOR: &str = "  ";
+            println!(
+                "{show_title} — ep{} — dn{}{details}",
+                show_obj.episode, show_obj.downloaded
+            );
+            if let Some(link) = show_obj.link.as_ref() {
+                println!("{0}link: {1}", LONG_SEPARATOR, link);
+            } else {
+                println!("{0}link: empty", LONG_SEPARATOR);
+            }
+            if let Some(dlink) = show_obj.dlink.as_ref() {
+                println!("{0}dlink: {1}", LONG_SEPARATOR, dlink);
+            } else {
+                println!("{0}dlink: empty", LONG_SEPARATOR);
+            }
         }
         Ok(())
     }
 
-    pub fn remove(mut self, show_title: &ValidatedTitle) -> Result<(), &'static str> {
+    pub fn entries(&self) -> Vec<Entry> {
+        let mut entries: Vec<Entry> = self
+            .current
+            .iter()
+            .map(|(show_title, show)| Entry {
+                title: show_title.to_owned(),
+                status: show.status,
+                episode: Some(show.episode),
+                date: None,
+                rewatch: show.rewatch,
+            })
+            .collect();
+        entries.sort_by(|a, b| a.title.cmp(&b.title));
+        entries
+    }
+
+    pub fn change_total(mut self, show_title: &ValidatedTitle, total: Option<u32>) -> Result<(), String> {
+        self.get_mut_show(show_title).total = total;
+        self.save()
+    }
+
+    pub fn change_status(mut self, show_title: &ValidatedTitle, status: Status) -> Result<(), String> {
+        self.get_mut_show(show_title).status = status;
+        self.save()
+    }
+
+    pub fn remove(mut self, show_title: &ValidatedTitle) -> Result<(), String> {
         self.current.remove(&show_title.0).unwrap();
         self.save()
     }
 
-    pub fn change_episode(
-        mut self,
-        show_title: &ValidatedTitle,
-        new_episode: u32,
-    ) -> Result<(), &'static str> {
-        self.get_mut_show(show_title).episode = new_episode;
+    /// Gives a show another title, keeping everything else about it.
+    pub fn rename(mut self, show_title: &ValidatedTitle, new_title: &str) -> Result<(), String> {
+        if self.current.contains_key(new_title) {
+            return Err(format!("you're already watching a show called '{new_title}'"));
+        }
+        let mut show = self.current.remove(&show_title.0).unwrap();
+        show.updated = Some(Utc::now());
+        self.current.insert(new_title.to_owned(), show);
         self.save()
     }
 
-    pub fn change_downloaded(
+    /// Sets both the episode you watched last and the last one downloaded, if given, in one save.
+    pub fn change_progress(
         mut self,
         show_title: &ValidatedTitle,
-        new_downloaded: u32,
-    ) -> Result<(), &'static str> {
-        self.get_mut_show(show_title).downloaded = new_downloaded;
+        new_episode: Option<u32>,
+        new_downloaded: Option<u32>,
+    ) -> Result<(), String> {
+        let show = self.get_mut_show(show_title);
+        if let Some(new_downloaded) = new_downloaded {
+            show.downloaded = new_downloaded;
+        }
+        if let Some(new_episode) = new_episode {
+            show.episode = new_episode;
+            show.resume = None;
+        }
         self.save()
     }
 
@@ -145,7 +471,7 @@ impl CurrentRepo {
         show_title: &ValidatedTitle,
         new_link: &str,
         is_dlink: bool,
-    ) -> Result<(), &'static str> {
+    ) -> Result<(), String> {
         if is_dlink {
             self.get_mut_show(show_title).dlink = Some(new_link.to_owned());
         } else {
@@ -154,17 +480,108 @@ impl CurrentRepo {
         self.save()
     }
 
+    pub fn change_schedule(
+        mut self,
+        show_title: &ValidatedTitle,
+        new_schedule: Option<Schedule>,
+    ) -> Result<(), String> {
+        self.get_mut_show(show_title).schedule = new_schedule;
+        self.save()
+    }
+
+    pub fn change_resume(mut self, show_title: &ValidatedTitle, position: Option<f64>) -> Result<(), String> {
+        self.get_mut_show(show_title).resume = position;
+        self.save()
+    }
+
+    pub fn change_download_dir(
+        mut self,
+        show_title: &ValidatedTitle,
+        download_dir: PathBuf,
+        pattern: String,
+    ) -> Result<(), String> {
+        let show = self.get_mut_show(show_title);
+        show.download_dir = Some(download_dir);
+        show.pattern = Some(pattern);
+        self.save()
+    }
+
+    /// Finds the latest downloaded episode of `show_title` (or of every show that has a download
+    /// directory, if `None`) by looking at the files in its download directory.
+    /// Returns every show whose `downloaded` would change, with the old and the new value, without
+    /// changing anything yet: that's what `apply_scan` is for.
+    pub fn scan(
+        &self,
+        show_title: Option<&ValidatedTitle>,
+    ) -> Result<Vec<(ValidatedTitle, u32, u32)>, String> {
+        let mut changes = Vec::new();
+        let mut titles: Vec<&String> = match show_title {
+            Some(show_title) => vec![&show_title.0],
+            None => self
+                .current
+                .iter()
+                .filter(|(_, show)| show.download_dir.is_some())
+                .map(|(show_title, _)| show_title)
+                .collect(),
+        };
+        titles.sort();
+        for show_title in titles {
+            let show = &self.current[show_title];
+            let (Some(download_dir), Some(pattern)) = (show.download_dir.as_ref(), show.pattern.as_ref())
+            else {
+                return Err(format!(
+                    "'{show_title}' has no download directory, set one with the `dir` subcommand"
+                ));
+            };
+            let files = find_episode_files(download_dir, pattern)?;
+            if let Some(downloaded) = highest_contiguous(&files, show.downloaded) {
+                changes.push((ValidatedTitle(show_title.to_owned()), show.downloaded, downloaded));
+            }
+        }
+        Ok(changes)
+    }
+
+    pub fn apply_scan(mut self, changes: &[(ValidatedTitle, u32, u32)]) -> Result<(), String> {
+        for (show_title, _, downloaded) in changes.iter() {
+            self.get_mut_show(show_title).downloaded = *downloaded;
+        }
+        self.save()
+    }
+
+    /// Every show you're watching that has a schedule, along with the latest episode of it that has aired by now.
+    pub fn get_aired(&self) -> Vec<(ValidatedTitle, u32)> {
+        let mut aired: Vec<_> = self
+            .current
+            .iter()
+            .filter(|(_, show)| show.status.is_watching())
+            .filter_map(|(show_title, show)| {
+                let schedule = show.schedule.as_ref()?;
+                Some((ValidatedTitle(show_title.to_owned()), schedule.aired(show.total)))
+            })
+            .collect();
+        aired.sort_by(|a, b| a.0 .0.cmp(&b.0 .0));
+        aired
+    }
+
     pub fn get_episode(&self, show_title: &ValidatedTitle) -> u32 {
         self.get_show(show_title).episode
     }
 
+    pub fn get_resume(&self, show_title: &ValidatedTitle) -> Option<f64> {
+        self.get_show(show_title).resume
+    }
+
     pub fn get_downloaded(&self, show_title: &ValidatedTitle) -> u32 {
         self.get_show(show_title).downloaded
     }
 
     pub fn get_next_episode_link(&self, show_title: &ValidatedTitle) -> String {
+        self.get_episode_link(show_title, self.get_episode(show_title) + 1)
+    }
+
+    pub fn get_episode_link(&self, show_title: &ValidatedTitle, episode: u32) -> String {
         let show = self.get_show(show_title);
-        format!("{}{}", show.resolve_link(false), show.episode + 1)
+        format!("{}{}", show.resolve_link(false), episode)
     }
 
     pub fn open_next_episode_link(&self, show_title: &ValidatedTitle) -> Result<(), String> {
@@ -182,6 +599,24 @@ impl CurrentRepo {
         Ok(())
     }
 
+    pub fn get_next_episode_file(&self, show_title: &ValidatedTitle) -> Result<PathBuf, String> {
+        let show = self.get_show(show_title);
+        let (Some(download_dir), Some(pattern)) = (show.download_dir.as_ref(), show.pattern.as_ref()) else {
+            return Err(format!(
+                "'{show_title}' has no download directory, set one with the `dir` subcommand"
+            ));
+        };
+        let next_episode = show.episode + 1;
+        find_episode_files(download_dir, pattern)?
+            .remove(&next_episode)
+            .ok_or_else(|| {
+                format!(
+                    "episode {next_episode} of '{show_title}' is not in {}",
+                    download_dir.display()
+                )
+            })
+    }
+
     pub fn open_link(&self, show_title: &ValidatedTitle, dlink: bool) -> Result<(), String> {
         open_in_browser(&self.get_link(show_title, dlink))?;
         Ok(())
@@ -192,23 +627,32 @@ impl CurrentRepo {
         show.resolve_link(dlink).to_owned()
     }
 
-    pub fn save(self) -> Result<(), &'static str> {
-        let mut entries: Vec<(String, Show)> = self.current.into_iter().collect();
-        entries.sort_by(|a, b| a.0.cmp(&b.0));
+    pub fn save(self) -> Result<(), String> {
+        let yaml = shows_to_yaml(self.current)?;
+        snapshot(&self.file_path)?;
+        fs::write(self.file_path.as_path(), yaml).map_err(|_| "failed to write to current.yml".into()) // we ensure the file exists on creation of the type
+    }
+}
 
-        let mut sorted_shows = IndexMap::new();
-        for (key, value) in entries {
-            sorted_shows.insert(key, value);
-        }
+fn format_time(time: DateTime<Utc>) -> String {
+    time.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()
+}
+
+/// Serializes the shows the way they're stored in current.yml: sorted by title.
+pub fn shows_to_yaml(shows: Shows) -> Result<String, &'static str> {
+    let mut entries: Vec<(String, Show)> = shows.into_iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
 
-        let yaml =
-            serde_yaml::to_string(&sorted_shows).map_err(|_| "couldn't serialize current model into yaml")?;
-        fs::write(self.file_path.as_path(), yaml).map_err(|_| "failed to write to current.yml") // we ensure the file exists on creation of the type
+    let mut sorted_shows = IndexMap::new();
+    for (key, value) in entries {
+        sorted_shows.insert(key, value);
     }
+
+    serde_yaml::to_string(&sorted_shows).map_err(|_| "couldn't serialize current model into yaml")
 }
 
 impl TryFrom<&Path> for CurrentRepo {
-    type Error = &'static str;
+    type Error = String;
 
     fn try_from(file_path: &Path) -> Result<Self, Self::Error> {
         let file = OpenOptions::new()
@@ -219,11 +663,244 @@ impl TryFrom<&Path> for CurrentRepo {
             .open(file_path)
             .map_err(|_| "could not create and/or open current.yml for reading")?;
         let reader = BufReader::new(file);
-        let current =
-            serde_yaml::from_reader(reader).map_err(|_| "couldn't deserialize current.yml into model")?;
+        let current = serde_yaml::from_reader(reader).map_err(|err| {
+            format!("couldn't deserialize current.yml into model: {err}\nrun `glaza doctor` for more")
+        })?;
         Ok(Self {
             current,
             file_path: file_path.to_path_buf(),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::TimeZone;
+    use chrono::Utc;
+    use tempfile::TempDir;
+
+    use super::Column;
+    use super::CurrentRepo;
+    use super::ListOptions;
+    use super::SortKey;
+    use crate::models::entry::Status;
+    use crate::models::ValidatedTitle;
+
+    const SHOWS: &str = "Frieren:
+  episode: 4
+  downloaded: 6
+  link: null
+  dlink: null
+  schedule: !weekly
+    weekday: Thu
+    time: 18:00:00
+    since: 2024-03-20
+Mushishi:
+  episode: 3
+  downloaded: 3
+  link: null
+  dlink: null
+  status: on-hold
+  schedule: !weekly
+    weekday: Sun
+    time: 01:00:00
+    since: 2024-03-20
+";
+
+    fn current(contents: &str) -> (TempDir, CurrentRepo) {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("current.yml");
+        fs::write(&path, contents).unwrap();
+        let current_model = CurrentRepo::try_from(path.as_path()).unwrap();
+        (dir, current_model)
+    }
+
+    fn reload(dir: &TempDir) -> CurrentRepo {
+        CurrentRepo::try_from(dir.path().join("current.yml").as_path()).unwrap()
+    }
+
+    #[test]
+    fn holding_and_resuming_keep_the_show() {
+        let (dir, current_model) = current(SHOWS);
+        let frieren = ValidatedTitle("Frieren".into());
+        current_model.change_status(&frieren, Status::OnHold).unwrap();
+        let current_model = reload(&dir);
+        let show = current_model.get_show(&frieren);
+        assert_eq!(show.status, Status::OnHold);
+        assert_eq!((show.episode, show.downloaded), (4, 6));
+        assert!(show.schedule.is_some());
+
+        let mushishi = ValidatedTitle("Mushishi".into());
+        current_model.change_status(&mushishi, Status::Watching).unwrap();
+        let current_model = reload(&dir);
+        assert_eq!(current_model.get_show(&mushishi).status, Status::Watching);
+        // watching is the default, so it isn't written down
+        let contents = fs::read_to_string(dir.path().join("current.yml")).unwrap();
+        assert_eq!(contents.matches("status: on-hold").count(), 1);
+    }
+
+    #[test]
+    fn renaming_refuses_titles_already_there() {
+        let (dir, current_model) = current(SHOWS);
+        let frieren = ValidatedTitle("Frieren".into());
+        assert!(current_model.rename(&frieren, "Mushishi").is_err());
+        let current_model = reload(&dir);
+        assert_eq!(current_model.get_episode(&frieren), 4);
+        assert_eq!(current_model.shows().len(), 2);
+
+        current_model.rename(&frieren, "Sousou no Frieren").unwrap();
+        let current_model = reload(&dir);
+        let titles: Vec<_> = current_model
+            .shows()
+            .into_iter()
+            .map(|(title, show)| (title.to_string(), show.episode))
+            .collect();
+        assert_eq!(titles, [
+            ("Mushishi".to_owned(), 3),
+            ("Sousou no Frieren".to_owned(), 4)
+        ]);
+    }
+
+    #[test]
+    fn shows_on_hold_have_no_aired_episodes() {
+        let (dir, current_model) = current(SHOWS);
+        let aired = current_model.get_aired();
+        assert_eq!(aired.len(), 1);
+        assert_eq!(aired[0].0.as_str(), "Frieren");
+        assert!(aired[0].1 > 4);
+
+        current_model
+            .change_status(&ValidatedTitle("Frieren".into()), Status::OnHold)
+            .unwrap();
+        assert!(reload(&dir).get_aired().is_empty());
+    }
+
+    const LISTED: &str = "Bleach:
+  episode: 10
+  downloaded: 12
+  link: https://website.com/bleach-
+  dlink: null
+  added: 2024-01-01T00:00:00Z
+  updated: 2024-01-05T00:00:00Z
+Frieren:
+  episode: 4
+  downloaded: 4
+  link: null
+  dlink: null
+  added: 2024-03-01T00:00:00Z
+  updated: 2099-01-01T00:00:00Z
+Monster:
+  episode: 20
+  downloaded: 21
+  link: null
+  dlink: null
+  added: 2023-06-01T00:00:00Z
+Mushishi:
+  episode: 3
+  downloaded: 3
+  link: null
+  dlink: null
+  status: on-hold
+";
+
+    fn options() -> ListOptions {
+        ListOptions {
+            links: false,
+            on_hold: false,
+            sort: SortKey::Title,
+            reverse: false,
+            has_backlog: false,
+            no_link: false,
+            stale: None,
+            format: None,
+            columns: Column::DEFAULT.to_vec(),
+            color: false,
+        }
+    }
+
+    fn titles(current_model: &CurrentRepo, options: &ListOptions) -> Vec<String> {
+        current_model
+            .listed(options)
+            .into_iter()
+            .map(|(title, _)| title.to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn shows_sort_by_every_key() {
+        let (_dir, current_model) = current(LISTED);
+        for (sort, expected) in [
+            (SortKey::Title, ["Bleach", "Frieren", "Monster"]),
+            (SortKey::Recent, ["Frieren", "Bleach", "Monster"]),
+            (SortKey::Progress, ["Monster", "Bleach", "Frieren"]),
+            (SortKey::Backlog, ["Bleach", "Monster", "Frieren"]),
+            (SortKey::Added, ["Frieren", "Bleach", "Monster"]),
+        ] {
+            let options = ListOptions { sort, ..options() };
+            assert_eq!(titles(&current_model, &options), expected, "{sort:?}");
+        }
+        let options = ListOptions {
+            sort: SortKey::Progress,
+            reverse: true,
+            ..options()
+        };
+        assert_eq!(titles(&current_model, &options), ["Frieren", "Bleach", "Monster"]);
+    }
+
+    #[test]
+    fn shows_filter_by_status_and_more() {
+        let (_dir, current_model) = current(LISTED);
+        let on_hold = ListOptions {
+            on_hold: true,
+            ..options()
+        };
+        assert_eq!(titles(&current_model, &on_hold), ["Mushishi"]);
+        let has_backlog = ListOptions {
+            has_backlog: true,
+            ..options()
+        };
+        assert_eq!(titles(&current_model, &has_backlog), ["Bleach", "Monster"]);
+        let no_link = ListOptions {
+            no_link: true,
+            ..options()
+        };
+        assert_eq!(titles(&current_model, &no_link), ["Frieren", "Monster"]);
+        // never having been updated counts as stale
+        let stale = ListOptions {
+            stale: Some(30),
+            ..options()
+        };
+        assert_eq!(titles(&current_model, &stale), ["Bleach", "Monster"]);
+    }
+
+    #[test]
+    fn changes_stamp_the_show() {
+        let (dir, current_model) = current("");
+        let added = Utc.with_ymd_and_hms(2024, 3, 1, 18, 0, 0).unwrap();
+        current_model
+            .new_show("Frieren", None, None, None, added)
+            .unwrap();
+        let current_model = reload(&dir);
+        let frieren = ValidatedTitle("Frieren".into());
+        let show = current_model.get_show(&frieren);
+        assert_eq!(show.added, Some(added));
+        let created = show.updated.unwrap();
+        assert!(created > added);
+
+        current_model
+            .change_link(&frieren, "https://website.com/frieren-", false)
+            .unwrap();
+        let current_model = reload(&dir);
+        let updated = current_model.get_show(&frieren).updated.unwrap();
+        assert!(updated >= created);
+
+        current_model.rename(&frieren, "Sousou no Frieren").unwrap();
+        let show = reload(&dir)
+            .get_show(&ValidatedTitle("Sousou no Frieren".into()))
+            .clone();
+        assert_eq!(show.added, Some(added));
+        assert!(show.updated.unwrap() >= updated);
+    }
+}
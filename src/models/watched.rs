@@ -1,39 +1,372 @@
+use std::fs;
 use std::fs::File;
 use std::fs::OpenOptions;
 use std::io::Read;
 use std::io::Write;
 use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::LazyLock;
+use std::sync::OnceLock;
 
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::FixedOffset;
+use chrono::Local;
+use chrono::NaiveDate;
+use chrono::NaiveTime;
+use chrono::TimeZone;
 use chrono::Utc;
+use clap::ValueEnum;
+use regex::Regex;
+use serde::Deserialize;
+
+use super::entry::Entry;
+use super::entry::Status;
+use super::write_atomically;
+use super::ValidatedTitle;
+use crate::backup::snapshot;
+use crate::format::Fields;
+use crate::format::Template;
+
+/// How dates are written in the watched list. Both are read, whichever is set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DateFormat {
+    /// `yy.mm.dd`
+    #[default]
+    Legacy,
+    /// `YYYY-MM-DD`
+    Iso,
+}
+
+impl DateFormat {
+    fn pattern(self) -> &'static str {
+        match self {
+            Self::Legacy => "%y.%m.%d",
+            Self::Iso => "%Y-%m-%d",
+        }
+    }
+}
+
+/// The date format, and the timezone that decides what day it is, set from the config.
+static DATES: OnceLock<(DateFormat, Option<FixedOffset>)> = OnceLock::new();
+
+/// Sets how new entries are dated, in `utc_offset`, or your local timezone without one.
+pub fn set_dates(format: DateFormat, utc_offset: Option<FixedOffset>) {
+    DATES.set((format, utc_offset)).ok();
+}
+
+fn utc_offset() -> Option<FixedOffset> {
+    DATES.get().and_then(|(_, utc_offset)| *utc_offset)
+}
+
+/// What day it is, in the configured timezone.
+pub fn today() -> NaiveDate {
+    match utc_offset() {
+        Some(utc_offset) => Utc::now().with_timezone(&utc_offset).date_naive(),
+        None => Local::now().date_naive(),
+    }
+}
+
+/// When `date` starts in `utc_offset`, or in your local timezone without one.
+fn midnight(date: NaiveDate, utc_offset: Option<FixedOffset>) -> Option<DateTime<Utc>> {
+    let midnight = date.and_time(NaiveTime::MIN);
+    let start = match utc_offset {
+        Some(utc_offset) => utc_offset.from_local_datetime(&midnight).single(),
+        None => Local
+            .from_local_datetime(&midnight)
+            .earliest()
+            .map(|start| start.fixed_offset()),
+    };
+    start.map(|start| start.with_timezone(&Utc))
+}
+
+fn format_date(date: NaiveDate) -> String {
+    let (format, _) = DATES.get().copied().unwrap_or_default();
+    date.format(format.pattern()).to_string()
+}
+
+/// A day given on the command line: `today`, `yesterday`, or a YYYY-MM-DD date.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Day {
+    #[default]
+    Today,
+    Yesterday,
+    On(NaiveDate),
+}
+
+impl Day {
+    /// Which day it is, which for `today` and `yesterday` depends on the configured timezone.
+    pub fn date(self) -> NaiveDate {
+        match self {
+            Self::Today => today(),
+            Self::Yesterday => today() - Duration::days(1),
+            Self::On(date) => date,
+        }
+    }
+
+    /// When the day starts, in the configured timezone, like the day itself.
+    pub fn start(self) -> Result<DateTime<Utc>, String> {
+        midnight(self.date(), utc_offset())
+            .ok_or_else(|| format!("{} doesn't start at midnight in your timezone", self.date()))
+    }
+}
+
+impl FromStr for Day {
+    type Err = String;
+
+    fn from_str(day: &str) -> Result<Self, Self::Err> {
+        match day {
+            "today" => Ok(Self::Today),
+            "yesterday" => Ok(Self::Yesterday),
+            _ => NaiveDate::parse_from_str(day, "%Y-%m-%d")
+                .map(Self::On)
+                .map_err(|_| format!("'{day}' is neither a YYYY-MM-DD date, `today` nor `yesterday`")),
+        }
+    }
+}
+
+/// How watching a show ended.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Outcome {
+    Finished,
+    Dropped,
+}
+
+/// Parses a date of the watched list, in either format.
+pub fn parse_date(date: &str) -> Option<NaiveDate> {
+    [DateFormat::Iso, DateFormat::Legacy]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(date, format.pattern()).ok())
+}
 
 pub struct WatchedRepo {
     file: File,
+    file_path: PathBuf,
 }
 
 impl WatchedRepo {
-    pub fn read(&mut self) -> Result<(), &'static str> {
+    pub fn read(&mut self, format: Option<&Template>) -> Result<(), String> {
+        if let Some(template) = format {
+            let rows: Vec<Fields> = self.entries()?.iter().map(Entry::fields).collect();
+            template.render(&rows).iter().for_each(|line| println!("{line}"));
+            return Ok(());
+        }
         let mut contents = String::new();
         if self.file.read_to_string(&mut contents).is_err() {
-            return Err("couldn't read watched file");
+            return Err("couldn't read watched file".into());
         }
-        println!("{}", contents.trim_end());
+        println!("{}", lines_by_date(&contents).join("\n"));
         Ok(())
     }
 
-    fn append_show(&mut self, show: &str) -> Result<(), &'static str> {
-        let today = Utc::now().format("%y.%m.%d");
-        if writeln!(self.file, "{} - {}", today, show).is_err() {
-            return Err("couldn't append to watched file");
+    fn append_show(&mut self, show: &str, date: NaiveDate) -> Result<(), String> {
+        snapshot(&self.file_path)?;
+        if writeln!(self.file, "{} - {}", format_date(date), show).is_err() {
+            return Err("couldn't append to watched file".into());
         }
         Ok(())
     }
 
-    pub fn finish(&mut self, show: &str) -> Result<(), &'static str> {
-        self.append_show(show)
+    /// Every show in the watched file, oldest first, whichever format its date is in.
+    pub fn entries(&self) -> Result<Vec<Entry>, String> {
+        let contents = fs::read_to_string(&self.file_path).map_err(|_| "couldn't read watched file")?;
+        let mut entries: Vec<Entry> = contents.lines().filter_map(parse_entry).collect();
+        entries.sort_by_key(|entry| entry.date);
+        Ok(entries)
+    }
+
+    /// How many times you've finished `show` before: dropping it doesn't count.
+    fn times_finished(&self, show: &str) -> Result<u32, String> {
+        let finished = self
+            .entries()?
+            .into_iter()
+            .filter(|entry| entry.status == Status::Completed && entry.title == show)
+            .count();
+        Ok(finished as u32)
+    }
+
+    /// Which rewatch starting `show` now would be, if you finished it before.
+    pub fn next_rewatch(&self, show: &str) -> Result<Option<u32>, String> {
+        let finished = self.times_finished(show)?;
+        Ok((finished > 0).then_some(finished + 1))
+    }
+
+    pub fn finish(&mut self, show: &str, rewatch: Option<u32>, date: NaiveDate) -> Result<(), String> {
+        self.append_show(&with_rewatch(show, rewatch), date)
+    }
+
+    pub fn drop(
+        &mut self,
+        latest_episode: u32,
+        show: &str,
+        rewatch: Option<u32>,
+        date: NaiveDate,
+    ) -> Result<(), String> {
+        self.append_show(
+            &format!(
+                "(dropped at ep {}) {}",
+                latest_episode,
+                with_rewatch(show, rewatch)
+            ),
+            date,
+        )
     }
 
-    pub fn drop(&mut self, latest_episode: u32, show: &str) -> Result<(), &'static str> {
-        self.append_show(&format!("(dropped at ep {}) {}", latest_episode, show))
+    pub fn normalize_show_pattern(&self, pattern: &str) -> Result<ValidatedTitle, String> {
+        let entries = self.entries()?;
+        ValidatedTitle::from_repeated(entries.iter().map(|entry| entry.title.as_str()), pattern)
+    }
+
+    /// Changes the last entry of `show`: its date, title, or how it ended (with the episode it
+    /// was dropped at). Any left out stay the same.
+    pub fn edit(
+        &mut self,
+        show: &ValidatedTitle,
+        date: Option<NaiveDate>,
+        title: Option<&str>,
+        outcome: Option<Outcome>,
+        episode: Option<u32>,
+    ) -> Result<(), String> {
+        self.rewrite_last(show, |line, mut entry| {
+            let date = match date {
+                Some(date) => format_date(date),
+                // keep the date as it's written
+                None => written_date(line).to_owned(),
+            };
+            if let Some(title) = title {
+                entry.title = title.to_owned();
+            }
+            match outcome {
+                Some(Outcome::Finished) => entry.status = Status::Completed,
+                Some(Outcome::Dropped) => entry.status = Status::Dropped,
+                None => (),
+            }
+            if episode.is_some() {
+                entry.episode = episode;
+            }
+            render_line(&date, &entry).map(Some)
+        })
+    }
+
+    /// Renames every entry of `title` to `new_title`, and tells how many there were.
+    pub fn rename(&mut self, title: &str, new_title: &str) -> Result<usize, String> {
+        let contents = fs::read_to_string(&self.file_path).map_err(|_| "couldn't read watched file")?;
+        let mut renamed = 0;
+        let mut lines = Vec::new();
+        for line in contents.lines() {
+            match parse_entry(line) {
+                Some(mut entry) if entry.title == title => {
+                    entry.title = new_title.to_owned();
+                    lines.push(render_line(written_date(line), &entry)?);
+                    renamed += 1;
+                },
+                _ => lines.push(line.to_owned()),
+            }
+        }
+        if renamed > 0 {
+            snapshot(&self.file_path)?;
+            write_atomically(&self.file_path, &(lines.join("\n") + "\n"))?;
+        }
+        Ok(renamed)
+    }
+
+    /// Removes the last entry of `show`.
+    pub fn remove(&mut self, show: &ValidatedTitle) -> Result<(), String> {
+        self.rewrite_last(show, |_, _| Ok(None))
+    }
+
+    /// Replaces the latest line of `show` (by date, and then by where it is, as backdated lines
+    /// are appended like any other) with what `rewrite` makes of it, or removes it if that's
+    /// nothing, and writes the file anew.
+    fn rewrite_last(
+        &mut self,
+        show: &ValidatedTitle,
+        rewrite: impl FnOnce(&str, Entry) -> Result<Option<String>, String>,
+    ) -> Result<(), String> {
+        let contents = fs::read_to_string(&self.file_path).map_err(|_| "couldn't read watched file")?;
+        let mut lines: Vec<String> = contents.lines().map(|line| line.to_owned()).collect();
+        let (index, entry) = lines
+            .iter()
+            .enumerate()
+            .filter_map(|(index, line)| {
+                let entry = parse_entry(line)?;
+                (entry.title == show.as_str()).then_some((index, entry))
+            })
+            .max_by_key(|(index, entry)| (entry.date, *index))
+            .ok_or_else(|| format!("'{show}' isn't in the watched list"))?;
+        match rewrite(&lines[index], entry)? {
+            Some(line) => lines[index] = line,
+            None => {
+                lines.remove(index);
+            },
+        }
+        let mut contents = lines.join("\n");
+        if !contents.is_empty() {
+            contents.push('\n');
+        }
+        snapshot(&self.file_path)?;
+        write_atomically(&self.file_path, &contents)
+    }
+}
+
+/// The date of a line, as it's written.
+fn written_date(line: &str) -> &str {
+    line.split_once(" - ").map(|(date, _)| date).unwrap_or_default()
+}
+
+/// The line of `entry`, the other way around from `parse_entry`.
+fn render_line(date: &str, entry: &Entry) -> Result<String, String> {
+    let show = with_rewatch(&entry.title, entry.rewatch);
+    match (entry.status, entry.episode) {
+        (Status::Dropped, Some(episode)) => Ok(format!("{date} - (dropped at ep {episode}) {show}")),
+        (Status::Dropped, None) => Err("which episode was it dropped at? say with `--episode`".into()),
+        _ => Ok(format!("{date} - {show}")),
+    }
+}
+
+/// The lines of the watched file in the order of their dates, whichever format they're in, as
+/// lines get added out of order by backdating, editing and syncing.
+fn lines_by_date(contents: &str) -> Vec<&str> {
+    let mut lines: Vec<&str> = contents.lines().filter(|line| !line.trim().is_empty()).collect();
+    lines.sort_by_key(|line| parse_entry(line).and_then(|entry| entry.date));
+    lines
+}
+
+static REWATCH_SUFFIX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r" \(rewatch #(\d+)\)$").unwrap());
+static DROPPED_PREFIX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\(dropped at ep (\d+)\) ").unwrap());
+
+/// Parses a line that looks like `23.09.03 - (dropped at ep 4) Title (rewatch #2)`, or
+/// `2023-09-03 - ...`.
+fn parse_entry(line: &str) -> Option<Entry> {
+    let (date, title) = line.split_once(" - ")?;
+    let date = parse_date(date);
+    let rewatch = REWATCH_SUFFIX
+        .captures(title)
+        .and_then(|captures| captures[1].parse().ok());
+    let title = REWATCH_SUFFIX.replace(title, "");
+    let (status, episode, title) = match DROPPED_PREFIX.captures(&title) {
+        Some(captures) => (
+            Status::Dropped,
+            captures[1].parse().ok(),
+            title[captures[0].len()..].to_owned(),
+        ),
+        None => (Status::Completed, None, title.into_owned()),
+    };
+    Some(Entry {
+        title,
+        status,
+        episode,
+        date,
+        rewatch,
+    })
+}
+
+fn with_rewatch(show: &str, rewatch: Option<u32>) -> String {
+    match rewatch {
+        Some(rewatch) => format!("{show} (rewatch #{rewatch})"),
+        None => show.to_owned(),
     }
 }
 
@@ -47,6 +380,156 @@ impl TryFrom<&Path> for WatchedRepo {
             .read(true)
             .open(file_path)
             .map_err(|_| "could not create and/or open the watched file")?;
-        Ok(Self { file })
+        Ok(Self {
+            file,
+            file_path: file_path.to_path_buf(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::FixedOffset;
+    use chrono::NaiveDate;
+
+    use super::lines_by_date;
+    use super::midnight;
+    use super::parse_entry;
+    use super::Outcome;
+    use super::WatchedRepo;
+    use crate::models::entry::Status;
+    use crate::models::ValidatedTitle;
+
+    #[test]
+    fn dropped_rewatches_parse() {
+        let entry = parse_entry("23.09.03 - (dropped at ep 4) Frieren (rewatch #2)").unwrap();
+        assert_eq!(entry.title, "Frieren");
+        assert_eq!(entry.status, Status::Dropped);
+        assert_eq!(entry.episode, Some(4));
+        assert_eq!(entry.rewatch, Some(2));
+        assert_eq!(entry.describe(), "dropped at ep 4 on 2023-09-03");
+    }
+
+    #[test]
+    fn days_start_in_the_configured_offset() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 22).unwrap();
+        let tokyo = FixedOffset::east_opt(9 * 3600);
+        assert_eq!(
+            midnight(date, tokyo).unwrap().to_rfc3339(),
+            "2024-03-21T15:00:00+00:00"
+        );
+        let utc = FixedOffset::east_opt(0);
+        assert_eq!(
+            midnight(date, utc).unwrap().to_rfc3339(),
+            "2024-03-22T00:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn both_date_formats_parse() {
+        let legacy = parse_entry("23.09.03 - Frieren").unwrap();
+        let iso = parse_entry("2023-09-03 - Frieren").unwrap();
+        assert_eq!(legacy.date, iso.date);
+        assert!(legacy.date.is_some());
+    }
+
+    #[test]
+    fn lines_print_by_date() {
+        let contents =
+            "23.09.03 - Mushishi\n2023-01-02 - Frieren\n\n2024-03-22 - Bleach\n24.03.22 - Monster\n";
+        assert_eq!(lines_by_date(contents), [
+            "2023-01-02 - Frieren",
+            "23.09.03 - Mushishi",
+            "2024-03-22 - Bleach",
+            "24.03.22 - Monster",
+        ]);
+    }
+
+    #[test]
+    fn edits_and_removals_change_the_last_entry() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let file_path = data_dir.path().join("watched.txt");
+        fs::write(
+            &file_path,
+            "23.09.03 - Frieren\n2024-01-02 - Bleach\n24.03.22 - Frieren (rewatch #2)\n",
+        )
+        .unwrap();
+        let mut watched = WatchedRepo::try_from(file_path.as_path()).unwrap();
+        let frieren = ValidatedTitle("Frieren".into());
+        watched
+            .edit(&frieren, None, None, Some(Outcome::Dropped), Some(4))
+            .unwrap();
+        watched.remove(&ValidatedTitle("Bleach".into())).unwrap();
+        assert_eq!(
+            fs::read_to_string(&file_path).unwrap(),
+            "23.09.03 - Frieren\n24.03.22 - (dropped at ep 4) Frieren (rewatch #2)\n"
+        );
+        assert!(watched.remove(&ValidatedTitle("Bleach".into())).is_err());
+    }
+
+    #[test]
+    fn backdated_entries_are_not_the_last() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let file_path = data_dir.path().join("watched.txt");
+        // the first time was only written down after the rewatch
+        fs::write(
+            &file_path,
+            "2024-03-22 - Frieren (rewatch #2)\n2024-01-02 - Bleach\n2023-09-03 - Frieren\n",
+        )
+        .unwrap();
+        let mut watched = WatchedRepo::try_from(file_path.as_path()).unwrap();
+        let frieren = ValidatedTitle("Frieren".into());
+        watched
+            .edit(&frieren, None, None, Some(Outcome::Dropped), Some(4))
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(&file_path).unwrap(),
+            "2024-03-22 - (dropped at ep 4) Frieren (rewatch #2)\n2024-01-02 - Bleach\n2023-09-03 - Frieren\n"
+        );
+        watched.remove(&frieren).unwrap();
+        assert_eq!(
+            fs::read_to_string(&file_path).unwrap(),
+            "2024-01-02 - Bleach\n2023-09-03 - Frieren\n"
+        );
+    }
+
+    #[test]
+    fn renaming_renames_every_entry() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let file_path = data_dir.path().join("watched.txt");
+        let contents =
+            "23.09.03 - Frieren\n2024-01-02 - (dropped at ep 4) Frieren (rewatch #2)\n2024-01-03 - Bleach\n";
+        fs::write(&file_path, contents).unwrap();
+        let mut watched = WatchedRepo::try_from(file_path.as_path()).unwrap();
+        assert_eq!(watched.rename("Monster", "Sousou no Frieren").unwrap(), 0);
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), contents);
+        assert_eq!(watched.rename("Frieren", "Sousou no Frieren").unwrap(), 2);
+        assert_eq!(
+            fs::read_to_string(&file_path).unwrap(),
+            "23.09.03 - Sousou no Frieren\n2024-01-02 - (dropped at ep 4) Sousou no Frieren (rewatch #2)\n2024-01-03 - Bleach\n"
+        );
+    }
+
+    #[test]
+    fn rewatches_count_the_finished_times() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let file_path = data_dir.path().join("watched.txt");
+        fs::write(&file_path, "2024-01-02 - Frieren Side Stories\n").unwrap();
+        let mut watched = WatchedRepo::try_from(file_path.as_path()).unwrap();
+        let day = |day| NaiveDate::from_ymd_opt(2024, 3, day).unwrap();
+        assert_eq!(watched.next_rewatch("Frieren").unwrap(), None);
+        watched.finish("Frieren", None, day(1)).unwrap();
+        assert_eq!(watched.next_rewatch("Frieren").unwrap(), Some(2));
+        watched.finish("Frieren", Some(2), day(2)).unwrap();
+        assert_eq!(watched.next_rewatch("Frieren").unwrap(), Some(3));
+        // dropping a rewatch doesn't make the next one another rewatch
+        watched.drop(5, "Frieren", Some(3), day(3)).unwrap();
+        assert_eq!(watched.next_rewatch("Frieren").unwrap(), Some(3));
+        assert_eq!(
+            fs::read_to_string(&file_path).unwrap(),
+            "2024-01-02 - Frieren Side Stories\n24.03.01 - Frieren\n24.03.02 - Frieren (rewatch #2)\n24.03.03 - (dropped at ep 5) Frieren (rewatch #3)\n"
+        );
     }
 }
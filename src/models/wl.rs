@@ -4,7 +4,12 @@ use std::io::Read;
 use std::path::Path;
 use std::path::PathBuf;
 
+use super::entry::Entry;
+use super::entry::Status;
 use super::ValidatedTitle;
+use crate::backup::snapshot;
+use crate::format::Fields;
+use crate::format::Template;
 
 pub struct WlRepo {
     contents: String,
@@ -26,10 +31,10 @@ impl WlRepo {
         let mut lines: Vec<String> = self.contents.lines().map(|line| line.to_owned()).collect();
         lines.push(what.to_owned());
         self.contents = lines.join("\n");
-        Ok(self.save()?)
+        self.save()
     }
 
-    pub fn remove(&mut self, show_title: &ValidatedTitle) -> Result<(), &'static str> {
+    pub fn remove(&mut self, show_title: &ValidatedTitle) -> Result<(), String> {
         self.contents = self
             .contents
             .lines()
@@ -39,12 +44,51 @@ impl WlRepo {
         self.save()
     }
 
-    pub fn list(&self) {
-        println!("{}", self.contents.trim_end())
+    /// Renames every line of `title` to `new_title`, and tells whether there were any.
+    pub fn rename(&mut self, title: &str, new_title: &str) -> Result<bool, String> {
+        if !self.contents.lines().any(|line| line == title) {
+            return Ok(false);
+        }
+        if self.contents.lines().any(|line| line == new_title) {
+            return Err(format!("watch later list already contains: '{}'", new_title));
+        }
+        self.contents = self
+            .contents
+            .lines()
+            .map(|line| if line == title { new_title } else { line })
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.save()?;
+        Ok(true)
+    }
+
+    pub fn entries(&self) -> Vec<Entry> {
+        self.contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Entry {
+                title: line.to_owned(),
+                status: Status::Planned,
+                episode: None,
+                date: None,
+                rewatch: None,
+            })
+            .collect()
     }
 
-    fn save(&mut self) -> Result<(), &'static str> {
-        fs::write(&self.file_path, &self.contents).map_err(|_| "couldn't write to watch later file")
+    pub fn list(&self, format: Option<&Template>) {
+        match format {
+            Some(template) => {
+                let rows: Vec<Fields> = self.entries().iter().map(Entry::fields).collect();
+                template.render(&rows).iter().for_each(|line| println!("{line}"));
+            },
+            None => println!("{}", self.contents.trim_end()),
+        }
+    }
+
+    fn save(&mut self) -> Result<(), String> {
+        snapshot(&self.file_path)?;
+        fs::write(&self.file_path, &self.contents).map_err(|_| "couldn't write to watch later file".into())
     }
 }
 
@@ -68,3 +112,25 @@ impl TryFrom<&Path> for WlRepo {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::WlRepo;
+
+    #[test]
+    fn renaming_refuses_titles_already_there() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("watch-later.txt");
+        fs::write(&path, "Frieren\nMushishi").unwrap();
+        let mut wl_model = WlRepo::try_from(path.as_path()).unwrap();
+        assert!(!wl_model.rename("Bleach", "Monster").unwrap());
+        assert!(wl_model.rename("Frieren", "Mushishi").is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Frieren\nMushishi");
+        assert!(wl_model.rename("Frieren", "Sousou no Frieren").unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Sousou no Frieren\nMushishi");
+    }
+}
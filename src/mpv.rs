@@ -0,0 +1,155 @@
+//! Talking to mpv over its JSON IPC socket, to find out how far into an episode you got.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// Seconds into the file.
+    pub position: f64,
+    /// Percent of the file played.
+    pub percent: f64,
+}
+
+#[cfg(unix)]
+pub use ipc::track_progress;
+
+#[cfg(unix)]
+mod ipc {
+    use std::io::BufRead;
+    use std::io::BufReader;
+    use std::io::Write;
+    use std::os::unix::net::UnixStream;
+    use std::path::Path;
+    use std::thread;
+    use std::time::Duration;
+
+    use serde_json::json;
+    use serde_json::Value;
+
+    use super::Progress;
+
+    const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+    struct Connection {
+        reader: BufReader<UnixStream>,
+        writer: UnixStream,
+        request_id: u64,
+    }
+
+    impl Connection {
+        fn open(socket: &Path) -> Option<Self> {
+            let writer = UnixStream::connect(socket).ok()?;
+            let reader = BufReader::new(writer.try_clone().ok()?);
+            Some(Self {
+                reader,
+                writer,
+                request_id: 0,
+            })
+        }
+
+        /// Returns `None` if the connection is gone, and `Some(None)` if mpv doesn't know the
+        /// property right now (like when no file is loaded yet).
+        fn get_property(&mut self, property: &str) -> Option<Option<f64>> {
+            self.request_id += 1;
+            let request = json!({ "command": ["get_property", property], "request_id": self.request_id });
+            writeln!(self.writer, "{request}").ok()?;
+            let mut line = String::new();
+            loop {
+                line.clear();
+                if self.reader.read_line(&mut line).ok()? == 0 {
+                    return None;
+                }
+                // mpv sends events on the same socket, that aren't responses to anything
+                let Ok(response) = serde_json::from_str::<Value>(&line) else {
+                    continue;
+                };
+                if response["request_id"].as_u64() == Some(self.request_id) {
+                    return Some(response["data"].as_f64());
+                }
+            }
+        }
+
+        fn progress(&mut self) -> Option<Option<Progress>> {
+            let position = self.get_property("time-pos")?;
+            let percent = self.get_property("percent-pos")?;
+            Some(
+                position
+                    .zip(percent)
+                    .map(|(position, percent)| Progress { position, percent }),
+            )
+        }
+    }
+
+    /// Polls the playback progress of the mpv listening on `socket` for as long as `is_running` says
+    /// it's running, and returns the last progress it saw.
+    pub fn track_progress(socket: &Path, mut is_running: impl FnMut() -> bool) -> Option<Progress> {
+        let mut connection = None;
+        let mut last_progress = None;
+        while is_running() {
+            if connection.is_none() {
+                connection = Connection::open(socket);
+            }
+            if let Some(current) = connection.as_mut() {
+                match current.progress() {
+                    Some(Some(progress)) => last_progress = Some(progress),
+                    Some(None) => (),
+                    None => break,
+                }
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        last_progress
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::io::BufRead;
+        use std::io::BufReader;
+        use std::io::Write;
+        use std::os::unix::net::UnixListener;
+        use std::thread;
+
+        use serde_json::json;
+        use serde_json::Value;
+
+        use super::track_progress;
+        use crate::mpv::Progress;
+
+        /// Pretends to be mpv playing through a file, answering `replies` requests before quitting.
+        fn fake_mpv(listener: UnixListener, replies: usize) {
+            let (stream, _) = listener.accept().unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            let mut reader = BufReader::new(stream);
+            writeln!(writer, r#"{{"event":"file-loaded"}}"#).unwrap();
+            for answered in 0..replies {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let request: Value = serde_json::from_str(&line).unwrap();
+                let played = (answered / 2 + 1) as f64;
+                let data = match request["command"][1].as_str().unwrap() {
+                    "time-pos" => json!(played * 60.0),
+                    "percent-pos" => json!(played * 10.0),
+                    _ => Value::Null,
+                };
+                let response =
+                    json!({ "data": data, "request_id": request["request_id"], "error": "success" });
+                writeln!(writer, "{response}").unwrap();
+            }
+        }
+
+        #[test]
+        fn tracks_progress_until_mpv_quits() {
+            let socket = std::env::temp_dir().join(format!("glaza-test-{}.sock", std::process::id()));
+            let _ = std::fs::remove_file(&socket);
+            let listener = UnixListener::bind(&socket).unwrap();
+            let server = thread::spawn(move || fake_mpv(listener, 6));
+            let progress = track_progress(&socket, || !server.is_finished());
+            std::fs::remove_file(&socket).unwrap();
+            assert_eq!(
+                progress,
+                Some(Progress {
+                    position: 180.0,
+                    percent: 30.0
+                })
+            );
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! Changes that both the command line and `serve` make, hooks and commits included.
+
+use crate::data::DataFiles;
+use crate::git::git_add_commit;
+use crate::hooks::run_hook;
+use crate::hooks::Event;
+use crate::hooks::Stage;
+use crate::models::log::LogRepo;
+use crate::models::show::CurrentRepo;
+use crate::models::watched::Day;
+use crate::models::watched::WatchedRepo;
+use crate::models::wl::WlRepo;
+use crate::models::ValidatedTitle;
+
+/// Marks `episode` of a show you're watching as the one you watched last.
+pub fn watch_episode(
+    data: &DataFiles,
+    git: bool,
+    current_model: CurrentRepo,
+    log_model: &mut LogRepo,
+    show: &ValidatedTitle,
+    episode: u32,
+) -> Result<(), String> {
+    update_progress(data, git, current_model, log_model, show, Some(episode), None)
+}
+
+/// Marks `episode` of a show you're watching as the last one downloaded.
+pub fn download_episode(
+    data: &DataFiles,
+    git: bool,
+    current_model: CurrentRepo,
+    log_model: &mut LogRepo,
+    show: &ValidatedTitle,
+    episode: u32,
+) -> Result<(), String> {
+    update_progress(data, git, current_model, log_model, show, None, Some(episode))
+}
+
+/// Marks `episode` as the one you watched last and `downloaded` as the last one downloaded, for
+/// whichever of them are given, with one save, one commit and one hook event: `episode`, or
+/// `download` if only `downloaded` is given.
+pub fn update_progress(
+    data: &DataFiles,
+    git: bool,
+    current_model: CurrentRepo,
+    log_model: &mut LogRepo,
+    show: &ValidatedTitle,
+    episode: Option<u32>,
+    downloaded: Option<u32>,
+) -> Result<(), String> {
+    let mut event = match episode {
+        Some(_) => Event::new("episode", show).with_show(current_model.get_show(show)),
+        None => Event::new("download", show).with_show(current_model.get_show(show)),
+    };
+    match (episode, downloaded) {
+        (Some(episode), downloaded) => {
+            event.new_episode = Some(episode);
+            event.new_downloaded = downloaded;
+        },
+        (None, Some(downloaded)) => {
+            event.old_episode = Some(current_model.get_downloaded(show));
+            event.new_episode = Some(downloaded);
+        },
+        (None, None) => return Err("there's neither an episode nor a downloaded episode to set".into()),
+    }
+    let previous_episode = current_model.get_episode(show);
+    run_hook(Stage::Pre, &event)?;
+    current_model.change_progress(show, episode, downloaded)?;
+    if let Some(episode) = episode {
+        log_model.record(show, previous_episode, episode)?;
+    }
+    if git {
+        let mut lines = Vec::new();
+        if let Some(downloaded) = downloaded {
+            lines.push(format!("download ep{downloaded} -> {show}"));
+        }
+        if let Some(episode) = episode {
+            lines.push(format!("watch ep{episode} -> {show}"));
+        }
+        git_add_commit(&data.data_dir, lines.join("\n"))?;
+    }
+    run_hook(Stage::Post, &event)
+}
+
+/// Puts a show in watch later.
+pub fn add_to_watch_later(
+    data: &DataFiles,
+    git: bool,
+    wl_model: &mut WlRepo,
+    show: &str,
+) -> Result<(), String> {
+    let event = Event::new("add", show);
+    run_hook(Stage::Pre, &event)?;
+    wl_model.add(show)?;
+    if git {
+        git_add_commit(&data.data_dir, format!("wl add -> {}", show))?;
+    }
+    run_hook(Stage::Post, &event)
+}
+
+/// Whether the show is finished or dropped, and on which day.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Ending {
+    Finish(Day),
+    Drop(Day),
+}
+
+/// Where the show being finished or dropped comes from.
+pub enum Source {
+    /// The shows you're watching.
+    Current(ValidatedTitle),
+    /// Watch later.
+    Grabbed(ValidatedTitle),
+    /// Nowhere, so it only gets put in the watched list.
+    Fresh(String),
+}
+
+/// Finishes or drops a show, moving it into the watched list.
+pub fn end(
+    data: &DataFiles,
+    git: bool,
+    ending: Ending,
+    current_model: CurrentRepo,
+    wl_model: &mut WlRepo,
+    watched_model: &mut WatchedRepo,
+    source: Source,
+) -> Result<(), String> {
+    let event_name = match ending {
+        Ending::Finish(_) => "finish",
+        Ending::Drop(_) => "drop",
+    };
+    let mut event = match &source {
+        Source::Current(show) => Event::new(event_name, show).with_show(current_model.get_show(show)),
+        Source::Grabbed(show) => Event::new(event_name, show),
+        Source::Fresh(show) => Event::new(event_name, show),
+    };
+    event.outcome = Some(match ending {
+        Ending::Finish(_) => "finished",
+        Ending::Drop(_) => "dropped",
+    });
+    let latest_episode = event.old_episode.unwrap_or_default();
+    let rewatch = match &source {
+        Source::Current(show) => current_model.get_show(show).rewatch,
+        _ => watched_model.next_rewatch(&event.show)?,
+    };
+    run_hook(Stage::Pre, &event)?;
+    match &source {
+        Source::Current(show) => current_model.remove(show)?,
+        Source::Grabbed(show) => wl_model.remove(show)?,
+        Source::Fresh(_) => (),
+    }
+    match ending {
+        Ending::Finish(day) => watched_model.finish(&event.show, rewatch, day.date())?,
+        Ending::Drop(day) => watched_model.drop(latest_episode, &event.show, rewatch, day.date())?,
+    }
+    if git {
+        let message = match ending {
+            Ending::Finish(_) => format!("finish -> {}", event.show),
+            Ending::Drop(_) => format!("drop at {latest_episode} -> {}", event.show),
+        };
+        git_add_commit(&data.data_dir, message)?;
+    }
+    run_hook(Stage::Post, &event)
+}
@@ -0,0 +1,298 @@
+//! The "what I watched this year" report: the shows finished and dropped in a year, month by
+//! month, along with a few numbers about them, as Markdown or html.
+
+use chrono::Datelike;
+use chrono::Local;
+use chrono::NaiveDate;
+use clap::ValueEnum;
+
+use crate::format::escape_html;
+use crate::models::entry::Entry;
+use crate::models::entry::Status;
+use crate::models::log::LogEntry;
+
+/// How many of the longest running shows are listed.
+const LONGEST: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ReportFormat {
+    Md,
+    Html,
+}
+
+/// A show finished in the year, and how long it took.
+struct Run<'a> {
+    entry: &'a Entry,
+    /// The day of the first episode of it you watched, according to the episode log.
+    started: NaiveDate,
+    finished: NaiveDate,
+}
+
+impl Run<'_> {
+    fn days(&self) -> i64 {
+        (self.finished - self.started).num_days()
+    }
+}
+
+struct Report<'a> {
+    year: i32,
+    /// The month of the year (1 to 12) and what was finished or dropped in it, in order.
+    months: Vec<(u32, Vec<&'a Entry>)>,
+    finished: usize,
+    dropped: Vec<&'a Entry>,
+    episodes: u32,
+    longest: Vec<Run<'a>>,
+}
+
+/// Renders the report of `year` out of the watched list and the episode log.
+pub fn report(year: i32, format: ReportFormat, watched: &[Entry], log: &[LogEntry]) -> String {
+    let report = Report::new(year, watched, log);
+    match format {
+        ReportFormat::Md => report.markdown(),
+        ReportFormat::Html => report.html(),
+    }
+}
+
+impl<'a> Report<'a> {
+    fn new(year: i32, watched: &'a [Entry], log: &[LogEntry]) -> Self {
+        let mut in_year: Vec<(usize, &Entry, NaiveDate)> = watched
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| Some((index, entry, entry.date?)))
+            .filter(|(_, _, date)| date.year() == year)
+            .collect();
+        in_year.sort_by_key(|(_, _, date)| *date);
+        let mut months: Vec<(u32, Vec<&Entry>)> = Vec::new();
+        for (_, entry, date) in in_year.iter() {
+            match months.last_mut() {
+                Some((month, entries)) if *month == date.month() => entries.push(entry),
+                _ => months.push((date.month(), vec![entry])),
+            }
+        }
+        let dropped: Vec<&Entry> = in_year
+            .iter()
+            .filter(|(_, entry, _)| entry.status == Status::Dropped)
+            .map(|(_, entry, _)| *entry)
+            .collect();
+        let episodes = log
+            .iter()
+            .filter(|entry| entry.timestamp.with_timezone(&Local).year() == year)
+            .map(|entry| entry.to.saturating_sub(entry.from))
+            .sum();
+        let mut longest: Vec<Run> = in_year
+            .iter()
+            .filter(|(_, entry, _)| entry.status == Status::Completed)
+            .filter_map(|(index, entry, finished)| {
+                // a rewatch starts after the previous time the show was in the watched list
+                let previous = watched[..*index]
+                    .iter()
+                    .rev()
+                    .find(|previous| previous.title == entry.title)
+                    .and_then(|previous| previous.date);
+                let started = log
+                    .iter()
+                    .filter(|log_entry| log_entry.title == entry.title)
+                    .map(|log_entry| log_entry.timestamp.with_timezone(&Local).date_naive())
+                    .find(|started| previous.is_none_or(|previous| *started > previous))
+                    .filter(|started| started <= finished)?;
+                Some(Run {
+                    entry,
+                    started,
+                    finished: *finished,
+                })
+            })
+            .collect();
+        longest.sort_by_key(|run| std::cmp::Reverse(run.days()));
+        longest.truncate(LONGEST);
+        Self {
+            year,
+            months,
+            finished: in_year.len() - dropped.len(),
+            dropped,
+            episodes,
+            longest,
+        }
+    }
+
+    fn markdown(&self) -> String {
+        let mut report = format!("# What I watched in {}\n\n", self.year);
+        report.push_str(&format!(
+            "{} finished, {} dropped, {} episodes watched.\n",
+            self.finished,
+            self.dropped.len(),
+            self.episodes
+        ));
+        for (month, entries) in self.months.iter() {
+            report.push_str(&format!("\n## {}\n\n", month_name(self.year, *month)));
+            for entry in entries {
+                report.push_str(&format!("- {}\n", describe(entry)));
+            }
+        }
+        if !self.longest.is_empty() {
+            report.push_str("\n## Longest running\n\n");
+            for run in self.longest.iter() {
+                report.push_str(&format!(
+                    "- {}: {}, from {} to {}\n",
+                    run.entry.title,
+                    days(run.days()),
+                    run.started,
+                    run.finished
+                ));
+            }
+        }
+        if !self.dropped.is_empty() {
+            report.push_str("\n## Dropped\n\n");
+            for entry in self.dropped.iter() {
+                report.push_str(&format!("- {}\n", dropped_at(entry)));
+            }
+        }
+        report
+    }
+
+    fn html(&self) -> String {
+        let mut report = format!(
+            "<!doctype html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>What I watched in {0}</title>\n</head>\n<body>\n<h1>What I watched in {0}</h1>\n",
+            self.year
+        );
+        report.push_str(&format!(
+            "<p>{} finished, {} dropped, {} episodes watched.</p>\n",
+            self.finished,
+            self.dropped.len(),
+            self.episodes
+        ));
+        for (month, entries) in self.months.iter() {
+            report.push_str(&format!("<h2>{}</h2>\n<ul>\n", month_name(self.year, *month)));
+            for entry in entries {
+                report.push_str(&format!("<li>{}</li>\n", escape_html(&describe(entry))));
+            }
+            report.push_str("</ul>\n");
+        }
+        if !self.longest.is_empty() {
+            report.push_str("<h2>Longest running</h2>\n<ol>\n");
+            for run in self.longest.iter() {
+                report.push_str(&format!(
+                    "<li>{}: {}, from {} to {}</li>\n",
+                    escape_html(&run.entry.title),
+                    days(run.days()),
+                    run.started,
+                    run.finished
+                ));
+            }
+            report.push_str("</ol>\n");
+        }
+        if !self.dropped.is_empty() {
+            report.push_str("<h2>Dropped</h2>\n<ul>\n");
+            for entry in self.dropped.iter() {
+                report.push_str(&format!("<li>{}</li>\n", escape_html(&dropped_at(entry))));
+            }
+            report.push_str("</ul>\n");
+        }
+        report.push_str("</body>\n</html>\n");
+        report
+    }
+}
+
+fn days(days: i64) -> String {
+    match days {
+        1 => "1 day".into(),
+        days => format!("{days} days"),
+    }
+}
+
+fn month_name(year: i32, month: u32) -> String {
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .map(|date| date.format("%B").to_string())
+        .unwrap_or_default()
+}
+
+/// Like `Frieren (rewatch #2)`, or `Mushishi, dropped at ep 4`.
+fn describe(entry: &Entry) -> String {
+    let mut line = entry.title.to_owned();
+    if let Some(rewatch) = entry.rewatch {
+        line.push_str(&format!(" (rewatch #{rewatch})"));
+    }
+    match (entry.status, entry.episode) {
+        (Status::Dropped, Some(episode)) => line.push_str(&format!(", dropped at ep {episode}")),
+        (Status::Dropped, None) => line.push_str(", dropped"),
+        _ => (),
+    }
+    line
+}
+
+fn dropped_at(entry: &Entry) -> String {
+    match entry.episode {
+        Some(episode) => format!("{} at ep {episode}", entry.title),
+        None => entry.title.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use chrono::TimeZone;
+    use chrono::Utc;
+
+    use super::report;
+    use super::ReportFormat;
+    use crate::models::entry::Entry;
+    use crate::models::entry::Status;
+    use crate::models::log::LogEntry;
+
+    fn log(title: &str, from: u32, to: u32, date: (i32, u32, u32)) -> LogEntry {
+        LogEntry {
+            timestamp: Utc.with_ymd_and_hms(date.0, date.1, date.2, 12, 0, 0).unwrap(),
+            from,
+            to,
+            title: title.into(),
+        }
+    }
+
+    #[test]
+    fn months_runs_and_drops() {
+        let watched = [
+            Entry {
+                title: "Bleach".into(),
+                status: Status::Completed,
+                episode: None,
+                date: NaiveDate::from_ymd_opt(2025, 12, 30),
+                rewatch: None,
+            },
+            Entry {
+                title: "Frieren".into(),
+                status: Status::Completed,
+                episode: None,
+                date: NaiveDate::from_ymd_opt(2026, 3, 22),
+                rewatch: None,
+            },
+            Entry {
+                title: "Mushishi".into(),
+                status: Status::Dropped,
+                episode: Some(4),
+                date: NaiveDate::from_ymd_opt(2026, 3, 25),
+                rewatch: None,
+            },
+            Entry {
+                title: "Monster".into(),
+                status: Status::Completed,
+                episode: None,
+                date: NaiveDate::from_ymd_opt(2026, 7, 1),
+                rewatch: None,
+            },
+        ];
+        let episodes = [
+            log("Bleach", 0, 366, (2025, 6, 1)),
+            log("Frieren", 0, 2, (2026, 1, 10)),
+            log("Frieren", 2, 28, (2026, 3, 22)),
+            log("Mushishi", 0, 4, (2026, 3, 20)),
+            log("Monster", 0, 74, (2026, 6, 30)),
+        ];
+        let markdown = report(2026, ReportFormat::Md, &watched, &episodes);
+        assert!(markdown.contains("2 finished, 1 dropped, 106 episodes watched."));
+        assert!(!markdown.contains("Bleach"));
+        assert!(markdown.contains("## March\n\n- Frieren\n- Mushishi, dropped at ep 4\n"));
+        assert!(markdown.contains(
+            "## Longest running\n\n- Frieren: 71 days, from 2026-01-10 to 2026-03-22\n- Monster: 1 day,"
+        ));
+        assert!(markdown.contains("## Dropped\n\n- Mushishi at ep 4\n"));
+    }
+}
@@ -0,0 +1,412 @@
+//! A small html dashboard of your lists, for checking and updating them from another device.
+//! It handles a single request at a time, loading the data files anew for each one, with the
+//! data directory locked so that glaza itself waits for it.
+//! Next to the dashboard is a JSON API, for scripts, see `api`.
+
+use std::collections::HashMap;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::ErrorKind;
+use std::io::Read;
+use std::io::Write;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use url::form_urlencoded;
+use url::Url;
+
+use crate::data::DataFiles;
+use crate::format::escape_html;
+use crate::models::log::LogRepo;
+use crate::models::show::CurrentRepo;
+use crate::models::watched::Day;
+use crate::models::watched::WatchedRepo;
+use crate::models::wl::WlRepo;
+use crate::ops;
+use crate::ops::Ending;
+use crate::ops::Source;
+
+mod api;
+
+/// Requests with bodies bigger than this are refused, the forms only ever send a title.
+const MAX_BODY: usize = 64 * 1024;
+/// How long a client gets to send its request and take the response, so that one that never does
+/// doesn't keep everyone else waiting.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Request {
+    method: String,
+    path: String,
+    /// The query string and the form body, together.
+    params: HashMap<String, String>,
+    /// The body as is, for the API.
+    body: Vec<u8>,
+    authorization: Option<String>,
+    host: Option<String>,
+    /// The `Origin` header, or the `Referer` one if there's no `Origin`.
+    origin: Option<String>,
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: String,
+}
+
+impl Response {
+    fn html(status: &'static str, body: String) -> Self {
+        Self {
+            status,
+            content_type: "text/html; charset=utf-8",
+            headers: Vec::new(),
+            body,
+        }
+    }
+
+    fn redirect(location: String) -> Self {
+        Self {
+            headers: vec![("Location", location)],
+            ..Self::html("303 See Other", String::new())
+        }
+    }
+
+    fn error(status: &'static str, message: &str) -> Self {
+        Self::html(
+            status,
+            page(&format!(
+                "<p class=\"error\">{}</p><p><a href=\"/\">back</a></p>",
+                escape_html(message)
+            )),
+        )
+    }
+}
+
+/// Serves the dashboard on `bind` until killed.
+/// The address it ends up listening on is printed first, which is useful with a port of 0.
+/// With a `token`, the API and the dashboard buttons want it, as a bearer token or as the password
+/// of basic auth, which browsers ask for by themselves.
+pub fn serve(data: &DataFiles, bind: &str, git: bool, token: Option<&str>) -> Result<(), String> {
+    let listener = TcpListener::bind(bind).map_err(|err| format!("couldn't listen on {bind}: {err}"))?;
+    let address = listener
+        .local_addr()
+        .map_err(|err| format!("couldn't tell what address is being listened on: {err}"))?;
+    println!("serving on http://{address}");
+    std::io::stdout().flush().ok();
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        if let Err(err) = handle(data, git, token, stream) {
+            eprintln!("{err}");
+        }
+    }
+    Ok(())
+}
+
+fn handle(data: &DataFiles, git: bool, token: Option<&str>, mut stream: TcpStream) -> Result<(), String> {
+    stream
+        .set_read_timeout(Some(TIMEOUT))
+        .and_then(|_| stream.set_write_timeout(Some(TIMEOUT)))
+        .map_err(|err| format!("couldn't set the connection timeouts: {err}"))?;
+    let response = match read_request(&mut stream) {
+        Ok(request) => match data.lock() {
+            Ok(_lock) => route(data, git, token, &request),
+            Err(err) => Response::error("500 Internal Server Error", &err),
+        },
+        Err(response) => response,
+    };
+    let mut head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    );
+    for (name, value) in response.headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    stream
+        .write_all(head.as_bytes())
+        .and_then(|_| stream.write_all(response.body.as_bytes()))
+        .map_err(|err| format!("couldn't respond: {err}"))
+}
+
+fn read_error(action: &'static str) -> impl Fn(io::Error) -> Response {
+    move |err| match err.kind() {
+        ErrorKind::WouldBlock | ErrorKind::TimedOut => {
+            Response::error("408 Request Timeout", &format!("took too long to send {action}"))
+        },
+        _ => Response::error("400 Bad Request", &format!("couldn't read {action}: {err}")),
+    }
+}
+
+fn read_request(stream: &mut TcpStream) -> Result<Request, Response> {
+    let bad_request = |message: &str| Response::error("400 Bad Request", message);
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader
+        .read_line(&mut request_line)
+        .map_err(read_error("the request"))?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(bad_request("malformed request line"));
+    };
+    let mut content_length = 0;
+    let mut authorization = None;
+    let mut host = None;
+    let mut origin = None;
+    let mut referer = None;
+    loop {
+        let mut header = String::new();
+        reader
+            .read_line(&mut header)
+            .map_err(read_error("the request headers"))?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| bad_request("malformed content length"))?;
+            } else if name.eq_ignore_ascii_case("authorization") {
+                authorization = Some(value.trim().to_owned());
+            } else if name.eq_ignore_ascii_case("host") {
+                host = Some(value.trim().to_owned());
+            } else if name.eq_ignore_ascii_case("origin") {
+                origin = Some(value.trim().to_owned());
+            } else if name.eq_ignore_ascii_case("referer") {
+                referer = Some(value.trim().to_owned());
+            }
+        }
+    }
+    if content_length > MAX_BODY {
+        return Err(bad_request("the request body is too big"));
+    }
+    let mut body = vec![0; content_length];
+    reader
+        .read_exact(&mut body)
+        .map_err(read_error("the request body"))?;
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let params = form_urlencoded::parse(query.as_bytes())
+        .chain(form_urlencoded::parse(&body))
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    Ok(Request {
+        method: method.to_owned(),
+        path: path.to_owned(),
+        params,
+        body,
+        authorization,
+        host,
+        origin: origin.or(referer),
+    })
+}
+
+/// Whether the request comes from a page of this server, going by its `Origin` (or `Referer`),
+/// so that other sites can't make your browser change things, with or without the token it
+/// remembers. Requests that come from no page at all, like the ones of scripts, are fine.
+fn same_origin(request: &Request) -> bool {
+    let Some(origin) = request.origin.as_deref() else {
+        return true;
+    };
+    let Ok(origin) = Url::parse(origin) else {
+        return false;
+    };
+    let authority = match (origin.host_str(), origin.port()) {
+        (Some(host), Some(port)) => format!("{host}:{port}"),
+        (Some(host), None) => host.to_owned(),
+        (None, _) => return false,
+    };
+    request
+        .host
+        .as_deref()
+        .is_some_and(|host| host.eq_ignore_ascii_case(&authority))
+}
+
+/// Whether the request has the `token`, if there is one.
+fn authorized(request: &Request, token: Option<&str>) -> bool {
+    let Some(token) = token else {
+        return true;
+    };
+    let Some(authorization) = request.authorization.as_deref() else {
+        return false;
+    };
+    if let Some(bearer) = authorization.strip_prefix("Bearer ") {
+        return bearer == token;
+    }
+    authorization
+        .strip_prefix("Basic ")
+        .and_then(|credentials| STANDARD.decode(credentials).ok())
+        .and_then(|credentials| String::from_utf8(credentials).ok())
+        .is_some_and(|credentials| {
+            credentials
+                .split_once(':')
+                .is_some_and(|(_, password)| password == token)
+        })
+}
+
+fn route(data: &DataFiles, git: bool, token: Option<&str>, request: &Request) -> Response {
+    if request.method != "GET" && !same_origin(request) {
+        return Response::error(
+            "403 Forbidden",
+            "changes can only come from this server's own pages",
+        );
+    }
+    if let Some(response) = api::route(data, git, token, request) {
+        return response;
+    }
+    if request.method == "POST" && !authorized(request, token) {
+        let mut response = Response::error("401 Unauthorized", "changing things needs the api token");
+        response
+            .headers
+            .push(("WWW-Authenticate", "Basic realm=\"glaza\"".into()));
+        return response;
+    }
+    let result = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/") => dashboard(data).map(|body| Response::html("200 OK", body)),
+        ("GET", "/next") => next_link(data, request),
+        ("POST", "/episode") => next_episode(data, git, request),
+        ("POST", "/finish") => end(data, git, request, Ending::Finish(Day::Today)),
+        ("POST", "/drop") => end(data, git, request, Ending::Drop(Day::Today)),
+        (_, "/" | "/next" | "/episode" | "/finish" | "/drop") => {
+            return Response::error("405 Method Not Allowed", "method not allowed")
+        },
+        _ => return Response::error("404 Not Found", "there's nothing here"),
+    };
+    result.unwrap_or_else(|err| Response::error("422 Unprocessable Entity", &err))
+}
+
+fn show_param(request: &Request) -> Result<&str, String> {
+    request
+        .params
+        .get("show")
+        .map(String::as_str)
+        .ok_or_else(|| "which show? the request has no `show`".into())
+}
+
+fn next_link(data: &DataFiles, request: &Request) -> Result<Response, String> {
+    let current_model = CurrentRepo::try_from(data.current.as_path())?;
+    let show = current_model.normalize_show_pattern(show_param(request)?)?;
+    if current_model.get_link(&show, false).is_empty() {
+        return Err(format!("{show} has no links"));
+    }
+    Ok(Response::redirect(current_model.get_next_episode_link(&show)))
+}
+
+fn next_episode(data: &DataFiles, git: bool, request: &Request) -> Result<Response, String> {
+    let current_model = CurrentRepo::try_from(data.current.as_path())?;
+    let mut log_model = LogRepo::try_from(data.log.as_path())?;
+    let show = current_model.normalize_show_pattern(show_param(request)?)?;
+    let episode = current_model.get_episode(&show) + 1;
+    ops::watch_episode(data, git, current_model, &mut log_model, &show, episode)?;
+    Ok(Response::redirect("/".into()))
+}
+
+fn end(data: &DataFiles, git: bool, request: &Request, ending: Ending) -> Result<Response, String> {
+    let current_model = CurrentRepo::try_from(data.current.as_path())?;
+    let mut wl_model = WlRepo::try_from(data.watch_later.as_path())?;
+    let mut watched_model = WatchedRepo::try_from(data.watched.as_path())?;
+    let show = current_model.normalize_show_pattern(show_param(request)?)?;
+    ops::end(
+        data,
+        git,
+        ending,
+        current_model,
+        &mut wl_model,
+        &mut watched_model,
+        Source::Current(show),
+    )?;
+    Ok(Response::redirect("/".into()))
+}
+
+fn dashboard(data: &DataFiles) -> Result<String, String> {
+    let current_model = CurrentRepo::try_from(data.current.as_path())?;
+    let wl_model = WlRepo::try_from(data.watch_later.as_path())?;
+    let watched_model = WatchedRepo::try_from(data.watched.as_path())?;
+    let (watching, on_hold): (Vec<_>, Vec<_>) = current_model
+        .shows()
+        .into_iter()
+        .partition(|(_, show)| show.status.is_watching());
+    let mut body = String::from("<h2>Watching</h2>\n<table>\n");
+    for (show_title, show) in watching {
+        let title = escape_html(show_title.as_str());
+        let button = |action: &str, label: &str| {
+            format!(
+                "<form method=\"post\" action=\"/{action}\"><input type=\"hidden\" name=\"show\" value=\"{title}\"><button>{label}</button></form>"
+            )
+        };
+        let next = if show.link.is_some() || show.dlink.is_some() {
+            format!(
+                "<a class=\"button\" href=\"/next?show={}\">next</a>",
+                form_urlencoded::byte_serialize(show_title.as_str().as_bytes()).collect::<String>()
+            )
+        } else {
+            String::new()
+        };
+        body.push_str(&format!(
+            "<tr><td>{title}</td><td>ep{} — dn{}</td><td>{}{next}{}{}</td></tr>\n",
+            show.episode,
+            show.downloaded,
+            button("episode", "+1"),
+            button("finish", "finish"),
+            button("drop", "drop"),
+        ));
+    }
+    body.push_str("</table>\n");
+    if !on_hold.is_empty() {
+        body.push_str("<h2>On hold</h2>\n<ul>\n");
+        for (show_title, show) in on_hold {
+            body.push_str(&format!(
+                "<li>{} — ep{}</li>\n",
+                escape_html(show_title.as_str()),
+                show.episode
+            ));
+        }
+        body.push_str("</ul>\n");
+    }
+    body.push_str("<h2>Watch later</h2>\n<ul>\n");
+    for entry in wl_model.entries() {
+        body.push_str(&format!("<li>{}</li>\n", escape_html(&entry.title)));
+    }
+    body.push_str("</ul>\n<h2>Watched</h2>\n<ul>\n");
+    for entry in watched_model.entries()?.iter().rev() {
+        body.push_str(&format!(
+            "<li>{} — {}</li>\n",
+            escape_html(&entry.title),
+            escape_html(&entry.describe())
+        ));
+    }
+    body.push_str("</ul>\n");
+    Ok(page(&body))
+}
+
+fn page(body: &str) -> String {
+    format!(
+        "<!doctype html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">
+<title>glaza</title>
+<style>
+body {{ font-family: sans-serif; margin: 1em; }}
+td {{ padding: 0.3em 0.5em; }}
+form {{ display: inline; }}
+button, .button {{ margin: 0.1em; padding: 0.3em 0.6em; font-size: 1em; }}
+.error {{ color: firebrick; }}
+</style>
+</head>
+<body>
+{body}</body>
+</html>
+"
+    )
+}
@@ -0,0 +1,217 @@
+//! The JSON API, for scripts rather than people:
+//!
+//! - `GET /shows`: the shows you're watching.
+//! - `GET /shows/{title}/next-link`: the link to the next episode of a show.
+//! - `PATCH /shows/{title}`: sets the `episode` and/or `downloaded` of a show, like `episode`
+//!   and `download` do.
+//! - `POST /shows/{title}/finish`: finishes a show.
+//! - `POST /watch-later`: puts the `title` in watch later.
+//!
+//! `{title}` is a percent-encoded pattern, matched like on the command line, and a pattern that
+//! matches several shows gets a 409 with the `candidates`.
+//! Errors are `{"error": "..."}`.
+
+use percent_encoding::percent_decode_str;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::json;
+use serde_json::Value;
+
+use super::authorized;
+use super::Request;
+use super::Response;
+use crate::data::DataFiles;
+use crate::models::log::LogRepo;
+use crate::models::show::CurrentRepo;
+use crate::models::show::Show;
+use crate::models::watched::Day;
+use crate::models::watched::WatchedRepo;
+use crate::models::wl::WlRepo;
+use crate::models::PatternError;
+use crate::models::ValidatedTitle;
+use crate::ops;
+use crate::ops::Ending;
+use crate::ops::Source;
+
+#[derive(Serialize)]
+struct ShowJson<'a> {
+    title: &'a str,
+    #[serde(flatten)]
+    show: &'a Show,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ShowPatch {
+    episode: Option<u32>,
+    downloaded: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WatchLaterPost {
+    title: String,
+}
+
+impl Response {
+    fn json(status: &'static str, body: Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            headers: Vec::new(),
+            body: body.to_string(),
+        }
+    }
+
+    fn json_error(status: &'static str, message: &str) -> Self {
+        Self::json(status, json!({ "error": message }))
+    }
+}
+
+/// Answers the requests for the API's paths, leaving the rest to the dashboard.
+pub(super) fn route(data: &DataFiles, git: bool, token: Option<&str>, request: &Request) -> Option<Response> {
+    let path = request.path.as_str();
+    if path != "/shows" && !path.starts_with("/shows/") && path != "/watch-later" {
+        return None;
+    }
+    if !authorized(request, token) {
+        let mut response = Response::json_error("401 Unauthorized", "a valid bearer token is needed");
+        response.headers.push(("WWW-Authenticate", "Bearer".into()));
+        return Some(response);
+    }
+    let segments: Vec<&str> = path[1..].split('/').collect();
+    let result = match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["shows"]) => list(data),
+        ("GET", ["shows", title, "next-link"]) => next_link(data, title),
+        ("PATCH", ["shows", title]) => patch(data, git, title, request),
+        ("POST", ["shows", title, "finish"]) => finish(data, git, title),
+        ("POST", ["watch-later"]) => add(data, git, request),
+        (_, ["shows"] | ["shows", _] | ["shows", _, "next-link" | "finish"] | ["watch-later"]) => Err(
+            Response::json_error("405 Method Not Allowed", "method not allowed"),
+        ),
+        _ => Err(Response::json_error("404 Not Found", "there's nothing here")),
+    };
+    Some(result.unwrap_or_else(|response| response))
+}
+
+fn unprocessable(err: impl AsRef<str>) -> Response {
+    Response::json_error("422 Unprocessable Entity", err.as_ref())
+}
+
+fn load_current(data: &DataFiles) -> Result<CurrentRepo, Response> {
+    CurrentRepo::try_from(data.current.as_path()).map_err(unprocessable)
+}
+
+fn resolve(current_model: &CurrentRepo, title: &str) -> Result<ValidatedTitle, Response> {
+    let pattern = percent_decode_str(title)
+        .decode_utf8()
+        .map_err(|_| Response::json_error("400 Bad Request", "the title isn't valid utf-8"))?;
+    current_model
+        .match_show_pattern(&pattern)
+        .map_err(|err| match err {
+            PatternError::NoMatch => Response::json_error(
+                "404 Not Found",
+                &format!("you aren't watching anything that matches '{pattern}'"),
+            ),
+            PatternError::Ambiguous { .. } => Response::json(
+                "409 Conflict",
+                json!({
+                    "error": format!("'{pattern}' matches more than one show"),
+                    "candidates": err.candidates(),
+                }),
+            ),
+        })
+}
+
+fn parse_body<'a, T: Deserialize<'a>>(request: &'a Request) -> Result<T, Response> {
+    serde_json::from_slice(&request.body)
+        .map_err(|err| Response::json_error("400 Bad Request", &format!("malformed request body: {err}")))
+}
+
+fn show_json(current_model: &CurrentRepo, show: &ValidatedTitle) -> Value {
+    json!(ShowJson {
+        title: show.as_str(),
+        show: current_model.get_show(show),
+    })
+}
+
+fn list(data: &DataFiles) -> Result<Response, Response> {
+    let current_model = load_current(data)?;
+    let shows: Vec<Value> = current_model
+        .shows()
+        .into_iter()
+        .map(|(title, show)| {
+            json!(ShowJson {
+                title: title.as_str(),
+                show,
+            })
+        })
+        .collect();
+    Ok(Response::json("200 OK", Value::Array(shows)))
+}
+
+fn next_link(data: &DataFiles, title: &str) -> Result<Response, Response> {
+    let current_model = load_current(data)?;
+    let show = resolve(&current_model, title)?;
+    if current_model.get_link(&show, false).is_empty() {
+        return Err(unprocessable(format!("{show} has no links")));
+    }
+    Ok(Response::json(
+        "200 OK",
+        json!({ "link": current_model.get_next_episode_link(&show) }),
+    ))
+}
+
+fn patch(data: &DataFiles, git: bool, title: &str, request: &Request) -> Result<Response, Response> {
+    let current_model = load_current(data)?;
+    let show = resolve(&current_model, title)?;
+    let patch: ShowPatch = parse_body(request)?;
+    if patch.episode.is_none() && patch.downloaded.is_none() {
+        return Err(unprocessable(
+            "there's neither an `episode` nor a `downloaded` to set",
+        ));
+    }
+    let mut log_model = LogRepo::try_from(data.log.as_path()).map_err(unprocessable)?;
+    ops::update_progress(
+        data,
+        git,
+        current_model,
+        &mut log_model,
+        &show,
+        patch.episode,
+        patch.downloaded,
+    )
+    .map_err(unprocessable)?;
+    let current_model = load_current(data)?;
+    Ok(Response::json("200 OK", show_json(&current_model, &show)))
+}
+
+fn finish(data: &DataFiles, git: bool, title: &str) -> Result<Response, Response> {
+    let current_model = load_current(data)?;
+    let show = resolve(&current_model, title)?;
+    let mut wl_model = WlRepo::try_from(data.watch_later.as_path()).map_err(unprocessable)?;
+    let mut watched_model = WatchedRepo::try_from(data.watched.as_path()).map_err(unprocessable)?;
+    let body = json!({ "title": show.as_str(), "outcome": "finished" });
+    ops::end(
+        data,
+        git,
+        Ending::Finish(Day::Today),
+        current_model,
+        &mut wl_model,
+        &mut watched_model,
+        Source::Current(show),
+    )
+    .map_err(unprocessable)?;
+    Ok(Response::json("200 OK", body))
+}
+
+fn add(data: &DataFiles, git: bool, request: &Request) -> Result<Response, Response> {
+    let post: WatchLaterPost = parse_body(request)?;
+    let title = post.title.trim();
+    if title.is_empty() {
+        return Err(unprocessable("the title is empty"));
+    }
+    let mut wl_model = WlRepo::try_from(data.watch_later.as_path()).map_err(unprocessable)?;
+    ops::add_to_watch_later(data, git, &mut wl_model, title).map_err(unprocessable)?;
+    Ok(Response::json("201 Created", json!({ "title": title })))
+}
@@ -1,29 +1,16 @@
 use std::env;
+#[cfg(unix)]
+use std::fs;
+use std::io;
+use std::io::Write;
 use std::path::Path;
+#[cfg(unix)]
+use std::process;
 use std::process::Command;
 
-pub fn git_add_commit(working_dir: &Path, message: String) -> Result<(), &'static str> {
-    if Command::new("git")
-        .arg("add")
-        .arg(".")
-        .current_dir(working_dir)
-        .output()
-        .is_err()
-    {
-        return Err("couldn't git add");
-    }
-    if Command::new("git")
-        .arg("commit")
-        .arg("-m")
-        .arg(message)
-        .current_dir(working_dir)
-        .output()
-        .is_err()
-    {
-        return Err("couldn't git commit");
-    }
-    Ok(())
-}
+#[cfg(unix)]
+use crate::mpv::track_progress;
+use crate::mpv::Progress;
 
 pub fn open_in_browser(link: &str) -> Result<(), &'static str> {
     let browser = get_browser()?;
@@ -34,24 +21,57 @@ pub fn open_in_browser(link: &str) -> Result<(), &'static str> {
     Ok(())
 }
 
-pub fn is_git_init(data_dir: &Path) -> bool {
-    data_dir.join(".git").exists()
+/// Plays `file` in your player, returning how far into it you got, if the player is mpv.
+/// In that case, `start` is the position (in seconds) to start playing from.
+pub fn play(file: &Path, start: Option<f64>) -> Result<Option<Progress>, String> {
+    let player = get_player();
+    let mut command = Command::new(&player);
+    command.arg(file);
+    #[cfg(unix)]
+    if Path::new(&player).file_stem().is_some_and(|stem| stem == "mpv") {
+        return play_in_mpv(command, &player, start);
+    }
+    let status = command
+        .status()
+        .map_err(|_| format!("player '{player}' is not in $PATH"))?;
+    if status.success() {
+        Ok(None)
+    } else {
+        Err(format!("player '{player}' exited unsuccessfully"))
+    }
 }
 
-pub fn git_init(data_dir: &Path) -> Result<(), String> {
-    if Command::new("git")
-        .arg("init")
-        .current_dir(data_dir)
-        .output()
-        .is_err()
-    {
-        Err(format!(
-            "couldn't git init the data directory in {}",
-            data_dir.display()
-        ))
+#[cfg(unix)]
+fn play_in_mpv(mut command: Command, player: &str, start: Option<f64>) -> Result<Option<Progress>, String> {
+    let socket = env::temp_dir().join(format!("glaza-mpv-{}.sock", process::id()));
+    command.arg(format!("--input-ipc-server={}", socket.display()));
+    if let Some(start) = start {
+        command.arg(format!("--start={start}"));
+    }
+    let mut child = command
+        .spawn()
+        .map_err(|_| format!("player '{player}' is not in $PATH"))?;
+    let progress = track_progress(&socket, || matches!(child.try_wait(), Ok(None)));
+    let status = child
+        .wait()
+        .map_err(|_| format!("couldn't wait for player '{player}' to exit"))?;
+    fs::remove_file(&socket).ok();
+    if status.success() {
+        Ok(progress)
     } else {
-        Ok(())
+        Err(format!("player '{player}' exited unsuccessfully"))
+    }
+}
+
+/// Asks a yes / no question on the terminal, defaulting to no.
+pub fn confirm(question: &str) -> bool {
+    eprint!("{question} [y/N] ");
+    io::stderr().flush().ok();
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        return false;
     }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
 }
 
 fn get_browser() -> Result<String, &'static str> {
@@ -60,3 +80,7 @@ fn get_browser() -> Result<String, &'static str> {
         Err(_) => Err("your $BROWSER environment variable is undefined"),
     }
 }
+
+fn get_player() -> String {
+    env::var("GLAZA_PLAYER").unwrap_or_else(|_| "mpv".into())
+}
@@ -0,0 +1,151 @@
+//! Lines of cells that line up in columns, no matter how wide the characters in them are.
+
+use std::env;
+use std::io::stdout;
+use std::io::IsTerminal;
+
+use clap::ValueEnum;
+use terminal_size::terminal_size;
+use terminal_size::Width;
+use unicode_width::UnicodeWidthChar;
+use unicode_width::UnicodeWidthStr;
+
+const SEPARATOR: &str = " — ";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color when printing to a terminal, unless `$NO_COLOR` is set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn should_color(self) -> bool {
+        match self {
+            Self::Auto => {
+                env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()) && stdout().is_terminal()
+            },
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Color {
+    Green,
+    Magenta,
+    Dim,
+}
+
+impl Color {
+    fn code(self) -> &'static str {
+        match self {
+            Self::Green => "\x1b[32m",
+            Self::Magenta => "\x1b[35m",
+            Self::Dim => "\x1b[2m",
+        }
+    }
+}
+
+/// How many columns wide the terminal is, if printing to one.
+pub fn terminal_width() -> Option<usize> {
+    terminal_size().map(|(Width(width), _)| width as usize)
+}
+
+#[derive(Default)]
+pub struct Table {
+    rows: Vec<(Vec<String>, Option<Color>)>,
+}
+
+impl Table {
+    pub fn push(&mut self, cells: Vec<String>, color: Option<Color>) {
+        self.rows.push((cells, color));
+    }
+
+    /// Renders every row, with the cells separated by ` — ` and padded to line up.
+    /// Empty cells at the end of a row are left out, and rows wider than `max_width`, like the
+    /// one of the terminal, get cut off.
+    pub fn render(&self, color: bool, max_width: Option<usize>) -> Vec<String> {
+        let mut widths: Vec<usize> = Vec::new();
+        for (cells, _) in self.rows.iter() {
+            for (index, cell) in cells.iter().enumerate() {
+                match widths.get_mut(index) {
+                    Some(width) => *width = (*width).max(cell.width()),
+                    None => widths.push(cell.width()),
+                }
+            }
+        }
+        self.rows
+            .iter()
+            .map(|(cells, row_color)| {
+                let filled = cells.len() - cells.iter().rev().take_while(|cell| cell.is_empty()).count();
+                let mut line = String::new();
+                for (index, cell) in cells[..filled].iter().enumerate() {
+                    if index > 0 {
+                        line.push_str(SEPARATOR);
+                    }
+                    line.push_str(cell);
+                    if index + 1 < filled {
+                        line.push_str(&" ".repeat(widths[index] - cell.width()));
+                    }
+                }
+                if let Some(max_width) = max_width {
+                    line = truncate(&line, max_width);
+                }
+                match row_color {
+                    Some(row_color) if color => format!("{}{line}\x1b[0m", row_color.code()),
+                    _ => line,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Cuts `line` down to `max_width` columns, ending it with `…` if anything was cut.
+fn truncate(line: &str, max_width: usize) -> String {
+    if line.width() <= max_width {
+        return line.to_owned();
+    }
+    let mut truncated = String::new();
+    let mut width = 0;
+    for char in line.chars() {
+        let char_width = char.width().unwrap_or_default();
+        if width + char_width + 1 > max_width {
+            break;
+        }
+        truncated.push(char);
+        width += char_width;
+    }
+    truncated.push('…');
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::truncate;
+    use super::Table;
+
+    #[test]
+    fn wide_characters_line_up() {
+        let mut table = Table::default();
+        table.push(vec!["葬送のフリーレン".into(), "ep3".into(), "".into()], None);
+        table.push(vec!["Pokémon".into(), "ep12".into(), "dn12".into()], None);
+        assert_eq!(table.render(false, None), [
+            "葬送のフリーレン — ep3",
+            "Pokémon          — ep12 — dn12"
+        ]);
+        assert_eq!(table.render(false, Some(24)), [
+            "葬送のフリーレン — ep3",
+            "Pokémon          — ep12…"
+        ]);
+    }
+
+    #[test]
+    fn truncation_counts_columns() {
+        assert_eq!(truncate("フリーレン", 6), "フリ…");
+        assert_eq!(truncate("Frieren", 7), "Frieren");
+    }
+}
@@ -0,0 +1,100 @@
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Command;
+use std::process::ExitStatus;
+use std::process::Stdio;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use tempfile::TempDir;
+
+/// Runs glaza, and gives up on it after a while, rather than hanging the tests.
+fn glaza(data_dir: &Path, config_dir: &Path, args: &[&str]) -> Option<ExitStatus> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_glaza"))
+        .args(args)
+        .env("GLAZA_DATA_DIR", data_dir)
+        .env("GLAZA_CONFIG_DIR", config_dir)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let started = Instant::now();
+    while started.elapsed() < Duration::from_secs(10) {
+        if let Some(status) = child.try_wait().unwrap() {
+            return Some(status);
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    child.kill().ok();
+    child.wait().ok();
+    None
+}
+
+/// A config directory with a hook running `script`.
+fn config_with_hook(hook_name: &str, script: &str) -> TempDir {
+    let config_dir = TempDir::new().unwrap();
+    fs::write(config_dir.path().join("config.yml"), "").unwrap();
+    let hooks_dir = config_dir.path().join("hooks");
+    fs::create_dir(&hooks_dir).unwrap();
+    let hook = hooks_dir.join(hook_name);
+    fs::write(&hook, format!("#!/bin/sh\n{script}\n")).unwrap();
+    fs::set_permissions(&hook, fs::Permissions::from_mode(0o755)).unwrap();
+    config_dir
+}
+
+#[test]
+fn post_hook_can_change_the_data_files() {
+    let data_dir = TempDir::new().unwrap();
+    let script = format!(
+        "exec '{}' download \"$GLAZA_SHOW\" 3",
+        env!("CARGO_BIN_EXE_glaza")
+    );
+    let config_dir = config_with_hook("post-episode", &script);
+
+    let status = glaza(data_dir.path(), config_dir.path(), &["start", "Frieren"]);
+    assert!(status.unwrap().success());
+    let status = glaza(data_dir.path(), config_dir.path(), &["episode", "Frieren", "1"]);
+    assert!(status.expect("glaza waited for the lock its hook held").success());
+    let current = fs::read_to_string(data_dir.path().join("current.yml")).unwrap();
+    assert!(current.contains("episode: 1"));
+    assert!(current.contains("downloaded: 3"));
+}
+
+#[test]
+fn only_pre_hooks_can_fail_the_action() {
+    let data_dir = TempDir::new().unwrap();
+    let config_dir = config_with_hook("post-start", "exit 1");
+    let status = glaza(data_dir.path(), config_dir.path(), &["start", "Frieren"]);
+    assert!(status.unwrap().success());
+    let current = fs::read_to_string(data_dir.path().join("current.yml")).unwrap();
+    assert!(current.contains("Frieren"));
+
+    let config_dir = config_with_hook("pre-start", "exit 1");
+    let status = glaza(data_dir.path(), config_dir.path(), &["start", "Mushishi"]);
+    assert!(!status.unwrap().success());
+    let current = fs::read_to_string(data_dir.path().join("current.yml")).unwrap();
+    assert!(!current.contains("Mushishi"));
+}
+
+#[test]
+fn changes_about_no_show_run_hooks_too() {
+    let data_dir = TempDir::new().unwrap();
+    let ran = data_dir.path().join("ran");
+    let config_dir = config_with_hook(
+        "post-doctor",
+        &format!("echo \"[$GLAZA_SHOW]\" > '{}'", ran.display()),
+    );
+    fs::write(data_dir.path().join("watch-later.txt"), "Bleach\n\nBleach\n").unwrap();
+    let status = glaza(data_dir.path(), config_dir.path(), &["doctor", "--fix"]);
+    assert!(status.unwrap().success());
+    assert_eq!(fs::read_to_string(&ran).unwrap(), "[]\n");
+
+    let config_dir = config_with_hook("pre-doctor", "exit 1");
+    fs::write(data_dir.path().join("watch-later.txt"), "Bleach\n\nBleach\n").unwrap();
+    let status = glaza(data_dir.path(), config_dir.path(), &["doctor", "--fix"]);
+    assert!(!status.unwrap().success());
+    let watch_later = fs::read_to_string(data_dir.path().join("watch-later.txt")).unwrap();
+    assert_eq!(watch_later, "Bleach\n\nBleach\n");
+}